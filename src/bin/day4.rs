@@ -1,43 +1,183 @@
+use std::env;
 use std::io;
 use std::io::Read;
-use std::fmt::Display;
+use std::process;
+use std::str::FromStr;
 
-use std::collections::HashSet;
+use num::{BigUint, One, Zero};
 
 type REPR = Vec<Card>;
 
 pub struct Card {
-    winning: Vec<i32>,
-    have: Vec<i32>,
+    winning: NumberSet,
+    have: NumberSet,
 }
 
 impl Card {
     fn num_matches(&self) -> u32 {
-        let win_set = HashSet::<_>::from_iter(&self.winning);
-        let have_set = HashSet::<_>::from_iter(&self.have);
-        return win_set.intersection(&have_set).count().try_into().unwrap();
+        return self.winning.intersection(&self.have).len();
     }
 }
 
-pub fn compute_1(input: REPR) -> i32 {
-    return input.into_iter()
-    .map(|c| {
-        let num_matches = c.num_matches();
-        if c.num_matches() == 0 {0} else {2_i32.pow(num_matches - 1)}
-    })
+// numbers below 128 are kept as bits, larger ones in a sorted list
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NumberSet {
+    bits: u128,
+    large: Vec<u32>,
+}
+
+impl NumberSet {
+    fn intersection(&self, other: &NumberSet) -> NumberSet {
+        let large = if self.large.is_empty() || other.large.is_empty() {
+            Vec::new()
+        } else {
+            self.large.iter().filter(|n| other.large.binary_search(n).is_ok()).copied().collect()
+        };
+        return NumberSet { bits: self.bits & other.bits, large };
+    }
+
+    fn len(&self) -> u32 {
+        return self.bits.count_ones() + self.large.len() as u32;
+    }
+}
+
+impl FromIterator<u32> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut bits = 0_u128;
+        let mut large = Vec::new();
+        for n in iter {
+            if n < u128::BITS {
+                bits |= 1 << n;
+            } else {
+                large.push(n);
+            }
+        }
+        large.sort_unstable();
+        large.dedup();
+        return NumberSet { bits, large };
+    }
+}
+
+pub enum ScoringRule {
+    Doubling,
+    Linear,
+    Fibonacci,
+    Table(Vec<u64>),
+}
+
+impl ScoringRule {
+    pub fn score(&self, num_matches: u32) -> BigUint {
+        match self {
+            // a table lists the score for 0, 1, 2, ... matches,
+            // more matches than listed keep the last score
+            ScoringRule::Table(scores) => {
+                let idx = (num_matches as usize).min(scores.len() - 1);
+                BigUint::from(scores[idx])
+            },
+            _ if num_matches == 0 => BigUint::zero(),
+            ScoringRule::Doubling => BigUint::one() << (num_matches - 1),
+            ScoringRule::Linear => BigUint::from(num_matches),
+            ScoringRule::Fibonacci => {
+                let (mut a, mut b) = (BigUint::zero(), BigUint::one());
+                for _ in 1..num_matches {
+                    let next = &a + &b;
+                    a = b;
+                    b = next;
+                }
+                b
+            },
+        }
+    }
+}
+
+impl FromStr for ScoringRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "doubling" => Ok(ScoringRule::Doubling),
+            "linear" => Ok(ScoringRule::Linear),
+            "fibonacci" => Ok(ScoringRule::Fibonacci),
+            _ => {
+                let table = s.strip_prefix("table:")
+                    .ok_or(format!("unknown scoring rule {}", s))?;
+                let scores: Vec<u64> = table.split(',')
+                    .map(|n| n.parse().map_err(|_| format!("invalid score {} in table", n)))
+                    .collect::<Result<_, _>>()?;
+                if scores.is_empty() {
+                    return Err("empty scoring table".to_owned());
+                }
+                Ok(ScoringRule::Table(scores))
+            },
+        }
+    }
+}
+
+pub fn total_score(input: &REPR, rule: &ScoringRule) -> BigUint {
+    return input.iter()
+    .map(|c| rule.score(c.num_matches()))
     .sum();
 }
 
-pub fn compute_2(input: REPR) -> i32 {
-    let wins_per_card: Vec<_> = input.into_iter().map(|c| c.num_matches()).collect();
+pub fn compute_1(input: REPR) -> BigUint {
+    return total_score(&input, &ScoringRule::Doubling);
+}
+
+pub struct CopyEdge {
+    from: usize,
+    to: usize,
+    copies: i32,
+}
+
+pub struct CopyCounts {
+    amounts_per_card: Vec<i32>,
+    trace: Option<Vec<CopyEdge>>,
+}
+
+impl CopyCounts {
+    pub fn to_csv(&self) -> String {
+        let mut result = String::from("from,to,copies\n");
+        for edge in self.trace.iter().flatten() {
+            result += &format!("{},{},{}\n", edge.from, edge.to, edge.copies);
+        }
+        return result;
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut result = String::from("digraph scratchcards {\n");
+        for (card, amount) in self.amounts_per_card.iter().enumerate() {
+            result += &format!("    {} [label=\"Card {}\\n{} total\"];\n", card + 1, card + 1, amount);
+        }
+        for edge in self.trace.iter().flatten() {
+            result += &format!("    {} -> {} [label=\"{}\"];\n", edge.from, edge.to, edge.copies);
+        }
+        result += "}\n";
+        return result;
+    }
+}
+
+pub fn copy_counts(input: &REPR, with_trace: bool) -> CopyCounts {
+    let wins_per_card: Vec<_> = input.iter().map(|c| c.num_matches()).collect();
     let mut amounts_per_card = vec![1; wins_per_card.len()];
+    let mut trace = if with_trace { Some(Vec::new()) } else { None };
     for (processed_card, wins) in wins_per_card.iter().enumerate() {
         for win in 1..=*wins {
             let copied_card: usize = (processed_card as u32 + win) as usize;
             amounts_per_card[copied_card] += amounts_per_card[processed_card];
+            if let Some(edges) = trace.as_mut() {
+                edges.push(CopyEdge {
+                    from: processed_card + 1,
+                    to: copied_card + 1,
+                    copies: amounts_per_card[processed_card],
+                });
+            }
         }
     }
-    return amounts_per_card.iter().sum();
+    return CopyCounts { amounts_per_card, trace };
+}
+
+pub fn compute_2(input: REPR) -> i32 {
+    return copy_counts(&input, false).amounts_per_card.iter().sum();
 }
 
 pub fn parse(input: &str) -> REPR {
@@ -45,21 +185,44 @@ pub fn parse(input: &str) -> REPR {
          .map(|l| {
             let (_, b) = l.split_once(": ").unwrap();
             let (win_str, have_str) = b.split_once(" | ").unwrap();
-            let winning: Vec<i32> = win_str.split(" ").filter_map(|n| n.parse().ok()).collect();
-            let have: Vec<i32> = have_str.split(" ").filter_map(|n| n.parse().ok()).collect();
+            let winning: NumberSet = win_str.split(" ").filter_map(|n| n.parse().ok()).collect();
+            let have: NumberSet = have_str.split(" ").filter_map(|n| n.parse().ok()).collect();
             Card { winning, have }
             })
          .collect();
 }
 
 fn main() {
-    read_and_write(parse, &[compute_1, compute_2]);
+    match env::args().nth(1).as_deref() {
+        Some("--trace-csv") => print!("{}", copy_counts(&parse(&read_input()), true).to_csv()),
+        Some("--trace-dot") => print!("{}", copy_counts(&parse(&read_input()), true).to_dot()),
+        Some("--scoring") => {
+            let rule = env::args().nth(2).ok_or("missing scoring rule".to_owned()).and_then(|r| r.parse::<ScoringRule>());
+            let rule = match rule {
+                Ok(rule) => rule,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                },
+            };
+            let input = parse(&read_input());
+            println!("{}", total_score(&input, &rule));
+            println!("{}", compute_2(input));
+        },
+        _ => {
+            let input = read_input();
+            println!("{}", compute_1(parse(&input)));
+            println!("{}", compute_2(parse(&input)));
+        },
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use indoc::indoc;
+    use std::collections::HashSet;
+    use std::time::Instant;
 
     const INPUT: &str = indoc! {"
         Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
@@ -72,25 +235,120 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(compute_1(parse(INPUT)), 13);
+        assert_eq!(compute_1(parse(INPUT)), BigUint::from(13_u32));
+    }
+
+    #[test]
+    fn test_scoring_rules() {
+        let cards = parse(INPUT);
+        let score = |rule: &str| total_score(&cards, &rule.parse().unwrap());
+        assert_eq!(score("doubling"), BigUint::from(13_u32));
+        assert_eq!(score("linear"), BigUint::from(4_u32 + 2 + 2 + 1));
+        assert_eq!(score("fibonacci"), BigUint::from(3_u32 + 1 + 1 + 1));
+        assert_eq!(score("table:0,10,20"), BigUint::from(20_u32 + 20 + 20 + 10));
+        assert_eq!(score("table:5,10,20"), BigUint::from(20_u32 + 20 + 20 + 10 + 5 + 5));
+        assert_eq!(ScoringRule::Table(vec![5, 10]).score(0), BigUint::from(5_u32));
+        assert!("table:".parse::<ScoringRule>().is_err());
+        assert!("squares".parse::<ScoringRule>().is_err());
+
+        assert_eq!(ScoringRule::Doubling.score(40), BigUint::from(1_u64 << 39));
+        assert_eq!(ScoringRule::Doubling.score(100), BigUint::one() << 99);
+        assert_eq!(ScoringRule::Fibonacci.score(93), BigUint::from(12200160415121876738_u64));
+    }
+
+    #[test]
+    fn test_number_set() {
+        let a: NumberSet = [1, 5, 99, 127].into_iter().collect();
+        let b: NumberSet = [5, 6, 127].into_iter().collect();
+        assert_eq!(a.len(), 4);
+        assert_eq!(a.intersection(&b), [5, 127].into_iter().collect());
+        assert_eq!(parse(INPUT)[0].num_matches(), 4);
+
+        let c: NumberSet = [200, 5, 4_000_000_000, 200].into_iter().collect();
+        assert_eq!(c.len(), 3);
+        assert_eq!(c.intersection(&b), [5].into_iter().collect());
+        assert_eq!(c.intersection(&[200, 201, 4_000_000_000].into_iter().collect()), [200, 4_000_000_000].into_iter().collect());
+        assert_eq!(parse("Card 1: 1 2 | 1 200\nCard 2: 300 2 | 300 5")[1].num_matches(), 1);
+    }
+
+    fn synthetic_input(num_cards: usize) -> String {
+        let mut state = 0x2545F4914F6CDD1D_u64;
+        let mut next_num = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % 100
+        };
+        let mut input = String::new();
+        for id in 1..=num_cards {
+            let winning = (0..10).map(|_| format!("{:2}", next_num())).collect::<Vec<_>>().join(" ");
+            let have = (0..25).map(|_| format!("{:2}", next_num())).collect::<Vec<_>>().join(" ");
+            input += &format!("Card {}: {} | {}\n", id, winning, have);
+        }
+        return input;
+    }
+
+    // run with `cargo test --release --bin day4 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_num_matches() {
+        let input = synthetic_input(2_000_000);
+        let as_vecs: Vec<(Vec<u32>, Vec<u32>)> = input.lines()
+            .map(|l| {
+                let (_, b) = l.split_once(": ").unwrap();
+                let (win_str, have_str) = b.split_once(" | ").unwrap();
+                (win_str.split(" ").filter_map(|n| n.parse().ok()).collect(),
+                 have_str.split(" ").filter_map(|n| n.parse().ok()).collect())
+            })
+            .collect();
+        let cards = parse(&input);
+
+        let start = Instant::now();
+        let hash_set_total: u64 = as_vecs.iter()
+            .map(|(winning, have)| {
+                let win_set = HashSet::<_>::from_iter(winning);
+                let have_set = HashSet::<_>::from_iter(have);
+                win_set.intersection(&have_set).count() as u64
+            })
+            .sum();
+        let hash_set_time = start.elapsed();
+
+        let start = Instant::now();
+        let bitset_total: u64 = cards.iter().map(|c| c.num_matches() as u64).sum();
+        let bitset_time = start.elapsed();
+
+        assert_eq!(hash_set_total, bitset_total);
+        println!("{} cards: HashSet {:?}, bitset {:?} ({:.0}x)",
+            cards.len(), hash_set_time, bitset_time,
+            hash_set_time.as_secs_f64() / bitset_time.as_secs_f64());
     }
 
     #[test]
     fn test_part2() {
         assert_eq!(compute_2(parse(INPUT)), 30);
     }
+
+    #[test]
+    fn test_copy_trace() {
+        let counts = copy_counts(&parse(INPUT), true);
+        assert_eq!(counts.amounts_per_card, vec![1, 2, 4, 8, 14, 1]);
+
+        let csv = counts.to_csv();
+        assert!(csv.starts_with("from,to,copies\n1,2,1\n1,3,1\n"));
+        assert!(csv.contains("\n4,5,8\n"));
+        assert_eq!(csv.lines().count(), 1 + 4 + 2 + 2 + 1);
+
+        assert!(counts.to_dot().contains("    3 -> 4 [label=\"4\"];"));
+        assert!(copy_counts(&parse(INPUT), false).trace.is_none());
+    }
 }
 
-fn read_and_write<T, S: Display>(parse: fn (&str) -> T, compute: &[fn(T) -> S] ) {
+fn read_input() -> String {
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
-
-    for f in compute {
-        let result = f(parse(&input));
-        println!("{}", result);    
-    }
-}
\ No newline at end of file
+    return input;
+}
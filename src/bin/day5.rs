@@ -1,48 +1,125 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
 use std::io;
-use std::convert::identity;
 use std::io::Read;
+use std::process;
+use std::fmt;
 use std::fmt::Display;
 
+use itertools::Itertools;
 use nom::IResult;
-use nom::bytes::complete::{tag, take_while, take_until};
-use nom::combinator::map_res;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{digit1, space0, space1};
+use nom::combinator::{all_consuming, map_res};
 use nom::multi::separated_list0;
-use nom::sequence::tuple;
+use nom::sequence::{pair, preceded, separated_pair, terminated, tuple};
+
+use adventofcode2023::interval_map::{merge, Interval, IntervalMap, Piece};
 
 type REPR = Almanac;
 
 pub struct Almanac {
     seeds: Vec<u64>,
-    seeds_as_ranges: Vec<Range>,
+    seeds_as_ranges: Vec<Interval>,
     maps: Vec<ConversionMap>,
 }
 
 impl Almanac {
-    fn conversion_for(&self, step: &str) -> Option<&ConversionMap> {
-        self.maps.iter().find(|&m| m.from == step)
+    fn categories(&self) -> Vec<&str> {
+        return self.maps.iter()
+            .flat_map(|m| [m.from.as_str(), m.to.as_str()])
+            .unique()
+            .collect();
+    }
+
+    fn conversions_from<'a>(&'a self, step: &'a str) -> impl Iterator<Item = &'a ConversionMap> {
+        self.maps.iter().filter(move |m| m.from == step)
     }
 
-    fn convert<'a>(&'a self, t: TypeRange<'a>) -> TypeRange {
-        let mut result = t;
-        while let Some(conversion) = self.conversion_for(result.step) {
-            result = conversion.map(result);
+    // shortest chain of conversion maps leading from one category to another
+    fn path<'a>(&'a self, from: &'a str, to: &'a str) -> Result<Vec<&'a ConversionMap>, String> {
+        let categories = self.categories();
+        for category in [from, to] {
+            if !categories.contains(&category) {
+                return Err(format!("unknown category {}", category));
+            }
         }
-        return result;
+
+        let mut reached_by: HashMap<&str, Option<&ConversionMap>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(step) = queue.pop_front() {
+            if step == to {
+                let mut path = Vec::new();
+                let mut step = to;
+                while let Some(conversion) = reached_by[step] {
+                    path.push(conversion);
+                    step = &conversion.from;
+                }
+                path.reverse();
+                return Ok(path);
+            }
+            for conversion in self.conversions_from(step) {
+                if !reached_by.contains_key(conversion.to.as_str()) {
+                    reached_by.insert(&conversion.to, Some(conversion));
+                    queue.push_back(&conversion.to);
+                }
+            }
+        }
+        return Err(format!("no conversion path from {} to {}", from, to));
+    }
+
+    fn find_cycle(&self) -> Option<Vec<&str>> {
+        fn visit<'a>(almanac: &'a Almanac, step: &'a str, stack: &mut Vec<&'a str>, done: &mut HashSet<&'a str>) -> Option<Vec<&'a str>> {
+            if let Some(pos) = stack.iter().position(|s| *s == step) {
+                let mut cycle = stack[pos..].to_vec();
+                cycle.push(step);
+                return Some(cycle);
+            }
+            if !done.insert(step) {
+                return None;
+            }
+            stack.push(step);
+            for conversion in almanac.conversions_from(step) {
+                if let Some(cycle) = visit(almanac, &conversion.to, stack, done) {
+                    return Some(cycle);
+                }
+            }
+            stack.pop();
+            return None;
+        }
+
+        let mut done = HashSet::new();
+        for category in self.categories() {
+            if let Some(cycle) = visit(self, category, &mut Vec::new(), &mut done) {
+                return Some(cycle);
+            }
+        }
+        return None;
     }
 
-    fn convert_seed(&self, seed_num: u64) -> u64 {
-        let location = self.convert(TypeRange { step: "seed", ranges: vec![Range::new(seed_num, 1)] });
-        assert_eq!(location.step, "location");
-        assert_eq!(location.ranges.len(), 1);
-        let location = &location.ranges[0];
-        assert_eq!(location.length, 1);
-        return location.start;
+    fn compose(&self, from: &str, to: &str) -> Result<IntervalMap, String> {
+        return self.path(from, to)?.iter()
+            .try_fold(IntervalMap::identity(), |acc, m| Ok(acc.compose(&m.interval_map()?)));
     }
 
-    fn convert_seed_range(&self, seed_range: Range) -> Vec<Range> {
-        let start = TypeRange { step: "seed", ranges: vec![seed_range] };
-        let result = self.convert(start);
-        return result.ranges;
+    fn seed_to_location(&self) -> Result<IntervalMap, String> {
+        return self.compose("seed", "location");
+    }
+
+    fn seeds_for_locations(&self, locations: Interval) -> Result<Vec<Interval>, String> {
+        return Ok(self.seed_to_location()?.preimage(locations));
+    }
+
+    fn seeds_reaching_lowest_location(&self) -> Result<(u64, Vec<Interval>), String> {
+        let seed_to_location = self.seed_to_location()?;
+        let lowest = self.seeds_as_ranges.iter()
+            .flat_map(|r| seed_to_location.map_interval(*r))
+            .map(|r| r.start)
+            .min().ok_or("no seeds")?;
+        let seeds = seed_to_location.preimage(Interval::with_length(lowest, 1)).into_iter()
+            .flat_map(|s| self.seeds_as_ranges.iter().filter_map(move |r| r.intersect(s)))
+            .collect();
+        return Ok((lowest, merge(seeds)));
     }
 }
 
@@ -50,194 +127,244 @@ pub struct ConversionMap {
     from: String,
     to: String,
     ranges: Vec<RangeMap>,
+    line: usize,
 }
 
 impl ConversionMap {
-    fn map(&self, input: TypeRange) -> TypeRange {
-        assert!(input.step == self.from);
-
-        let mut input_ranges = input.ranges.clone();
-        let mut result_ranges = Vec::new();
-        for map in &self.ranges {
-            let mut unhandled_ranges = Vec::new();
-            for range in input_ranges {
-                let (handled, unhandled) = map.map(range);
-                for r in handled {
-                    result_ranges.push(r);
+    fn interval_map(&self) -> Result<IntervalMap, String> {
+        let pieces: Vec<Piece> = self.ranges.iter()
+            .filter(|r| r.length > 0)
+            .map(|r| Ok(Piece { src: r.src(), offset: r.offset()? }))
+            .collect::<Result<_, String>>()?;
+        return Ok(IntervalMap::from_pieces(pieces));
+    }
+
+    fn lint(&self) -> Vec<Diagnostic> {
+        let mut result = Vec::new();
+        // the remaining checks only look at well-formed ranges, no-op ranges included
+        let mut well_formed = Vec::new();
+        for r in &self.ranges {
+            if r.length == 0 {
+                result.push(Diagnostic { line: r.line, message: "empty range".to_owned() });
+            } else if r.offset().is_err() {
+                result.push(Diagnostic { line: r.line, message: "offset does not fit in i64".to_owned() });
+            } else {
+                if r.src_start == r.dest_start {
+                    result.push(Diagnostic { line: r.line, message: "range maps onto itself".to_owned() });
                 }
-                for r in unhandled {
-                    unhandled_ranges.push(r);
-                }    
-            };
-            input_ranges = unhandled_ranges;
+                well_formed.push(r);
+            }
         }
-        for r in input_ranges {
-            result_ranges.push(r);
+
+        for (i, a) in well_formed.iter().enumerate() {
+            for b in &well_formed[i + 1..] {
+                if let Some(overlap) = a.src().intersect(b.src()) {
+                    result.push(Diagnostic {
+                        line: b.line,
+                        message: format!("source {} overlaps line {}, which takes precedence", overlap, a.line),
+                    });
+                }
+            }
         }
-        
-        TypeRange {
-            step: &self.to,
-            ranges: result_ranges
+
+        let sources = merge(well_formed.iter().map(|r| r.src()).collect());
+        for (a, b) in sources.iter().tuple_windows() {
+            result.push(Diagnostic {
+                line: self.line,
+                message: format!("gap {} in {}-to-{} is not mapped", Interval::new(a.end, b.start), self.from, self.to),
+            });
         }
+        return result;
     }
 }
 
 pub struct RangeMap {
-    offset: i64,
-    src: Range,
+    dest_start: u64,
+    src_start: u64,
+    length: u64,
+    line: usize,
 }
 
 impl RangeMap {
-    fn map(&self, range: Range) -> (Vec<Range>, Vec<Range>) {
-        let (before, to_map, after) = self.src.split(range);
-
-        let mapped = to_map.map(|r| Range::new(
-            (r.start as i64 + self.offset) as u64,
-            r.length
-        ));
+    fn src(&self) -> Interval {
+        return Interval::with_length(self.src_start, self.length);
+    }
 
-        let handled = vec![mapped].into_iter().filter_map(identity).collect();
-        let unhandled = vec![before, after].into_iter().filter_map(identity).collect();
-        return (handled, unhandled);
+    fn offset(&self) -> Result<i64, String> {
+        return i64::try_from(self.dest_start as i128 - self.src_start as i128)
+            .map_err(|_| format!("line {}: offset does not fit in i64", self.line));
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub struct Range {
-    start: u64,
-    length: u64,
-    end: u64,
+#[derive(PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    line: usize,
+    message: String,
 }
 
-impl Range {
-    fn new(start: u64, length: u64) -> Range {
-        assert!(length > 0);
-        Range {
-            start,
-            length,
-            end: start + length
-        }
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
     }
+}
 
-    fn end(&self) -> u64 {
-        return self.start + self.length;
-    }
-
-    fn split(&self, to_split: Range) -> (Option<Range>, Option<Range>, Option<Range>) {
-        let before = if to_split.start >= self.start {
-             None
-        } else {
-            Some(Range::new(to_split.start, (self.start-to_split.start).min(to_split.length)))
-        };
-
-        let intersect_from = to_split.start.max(self.start);
-        let intersect_to = to_split.end().min(self.end());
-        let intersect = if intersect_from < intersect_to {
-            Some(Range::new(intersect_from, intersect_to-intersect_from))
-        } else {
-            None
-        };
+pub fn lint(almanac: &Almanac) -> Vec<Diagnostic> {
+    let mut result: Vec<Diagnostic> = almanac.maps.iter().flat_map(|m| m.lint()).collect();
+    result.sort_by_key(|d| d.line);
+    return result;
+}
 
-        let after = if to_split.end() <= self.end() {
-            None
-        } else {
-            let start = self.end().max(to_split.start);
-            let length = to_split.end() - start;
-            assert!(length > 0, "{:?} {:?}", self, to_split);
-            Some(Range::new(start, length))
-        };
-       
-        return (before, intersect, after);
-    }
+pub fn compute_1(input: REPR) -> Result<u64, String> {
+    let seed_to_location = input.seed_to_location()?;
+    input.seeds.iter()
+    .map( |s| seed_to_location.get(*s))
+    .min().ok_or("no seeds".to_owned())
 }
 
-struct TypeRange<'a> {
-    step: &'a str,
-    ranges: Vec<Range>,
+pub fn compute_2(input: REPR) -> Result<u64, String> {
+    return Ok(input.seeds_reaching_lowest_location()?.0);
 }
 
-pub fn compute_1(input: REPR) -> u64 {
-    input.seeds.iter()
-    .map( |s| input.convert_seed(*s))
-    .min().unwrap()
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParseError {
+    MissingSeeds,
+    OddSeedCount(usize),
+    InvalidLine { line: usize, content: String },
+    RangeWithoutMap { line: usize },
+    RangeOverflow { line: usize },
 }
 
-pub fn compute_2(input: REPR) -> u64 {
-    input.seeds_as_ranges.iter()
-    .flat_map( |r| input.convert_seed_range(*r))
-    .map(|r| r.start)
-    .min().unwrap()
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingSeeds => write!(f, "input does not start with a seeds line"),
+            ParseError::OddSeedCount(n) => write!(f, "{} seeds cannot be read as ranges", n),
+            ParseError::InvalidLine { line, content } => write!(f, "line {}: cannot parse {:?}", line, content),
+            ParseError::RangeWithoutMap { line } => write!(f, "line {}: range outside of a map", line),
+            ParseError::RangeOverflow { line } => write!(f, "line {}: range end overflows u64", line),
+        }
+    }
 }
 
-pub fn parse(input: &str) -> REPR {
-    fn range(i: &str) -> IResult<&str, RangeMap> {
-        let (i, (dest_start, _, src_start, _,  length)) = tuple((
-            num,
-            tag(" "),
-            num,
-            tag(" "),
-            num,
-        ))(i)?;
-        let offset = dest_start as i64 - src_start as i64;
-        Ok((i, RangeMap { offset, src: Range::new(src_start, length) }))
+pub fn try_parse(input: &str) -> Result<REPR, ParseError> {
+    fn num(i: &str) -> IResult<&str, u64> {
+        map_res(digit1, |n: &str| n.parse::<u64>())(i)
     }
 
-    fn conversion_map(i: &str) -> IResult<&str, ConversionMap> {
-        let (i, from) = take_until("-to-")(i)?;
-        let (i, _) = tag("-to-")(i)?;
-        let (i, to) = take_until(" map:\n")(i)?;
-        let (i, _) = tag(" map:\n")(i)?;
-        let (i, ranges) = separated_list0(
-            tag("\n"),
-            range,
-        )(i)?;
-        Ok((i, ConversionMap { 
-            from: from.to_owned(),
-            to: to.to_owned(),
-            ranges
-         }))
+    fn category(i: &str) -> IResult<&str, &str> {
+        take_while1(|c: char| c.is_alphanumeric() || c == '_')(i)
     }
 
-    fn num(i: &str) -> IResult<&str, u64> {
-        map_res(
-            take_while(|c: char| c.is_digit(10)),
-            |n: &str| n.parse::<u64>()
+    fn seeds(i: &str) -> IResult<&str, Vec<u64>> {
+        preceded(
+            pair(tag("seeds:"), space0),
+            separated_list0(space1, num),
         )(i)
     }
 
-    fn seeds(i: &str) -> IResult<&str, Vec<u64>> {
-        let (i, _) = tag("seeds: ")(i)?;
-        let (i, result) = separated_list0(
-            tag(" "),
-            num,
-        )(i)?;
-        Ok((i, result))
+    fn header(i: &str) -> IResult<&str, (&str, &str)> {
+        terminated(
+            separated_pair(category, tag("-to-"), category),
+            pair(space1, tag("map:")),
+        )(i)
     }
 
-    fn all(i: &str) -> IResult<&str, Almanac> {
-        let (i, seeds) = seeds(i)?;
-        let (i, _) = tag("\n")(i)?;
-        let (i, _) = tag("\n")(i)?;
-        let (i, maps) = separated_list0(
-            tag("\n\n"),
-            conversion_map,
-        )(i)?;
-        let (i, _) = tag("\n")(i)?;
-
-        let seeds_as_ranges = seeds.chunks(2)
-                                .map(|x| Range::new(x[0], x[1]))
-                                .collect();
-
-        Ok((i, Almanac { seeds, seeds_as_ranges, maps } ))
+    fn range(i: &str) -> IResult<&str, (u64, u64, u64)> {
+        tuple((num, preceded(space1, num), preceded(space1, num)))(i)
     }
 
-    let (rest, result) = all(input).unwrap();
-    assert!(rest.is_empty());
-    return result;
+    // str::lines already strips the \r of \r\n line endings
+    let mut lines = input.lines()
+        .enumerate()
+        .map(|(idx, l)| (idx + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty());
+
+    let (seeds_line, (_, seeds)) = lines.next()
+        .and_then(|(line, l)| Some((line, all_consuming(seeds)(l).ok()?)))
+        .ok_or(ParseError::MissingSeeds)?;
+    if seeds.len() % 2 != 0 {
+        return Err(ParseError::OddSeedCount(seeds.len()));
+    }
+    if seeds.chunks(2).any(|x| x[0].checked_add(x[1]).is_none()) {
+        return Err(ParseError::RangeOverflow { line: seeds_line });
+    }
+
+    let mut maps: Vec<ConversionMap> = Vec::new();
+    for (line, l) in lines {
+        if let Ok((_, (from, to))) = all_consuming(header)(l) {
+            maps.push(ConversionMap {
+                from: from.to_owned(),
+                to: to.to_owned(),
+                ranges: Vec::new(),
+                line,
+            });
+        } else if let Ok((_, (dest_start, src_start, length))) = all_consuming(range)(l) {
+            let map = maps.last_mut().ok_or(ParseError::RangeWithoutMap { line })?;
+            if src_start.checked_add(length).is_none() || dest_start.checked_add(length).is_none() {
+                return Err(ParseError::RangeOverflow { line });
+            }
+            map.ranges.push(RangeMap { dest_start, src_start, length, line });
+        } else {
+            return Err(ParseError::InvalidLine { line, content: l.to_owned() });
+        }
+    }
+
+    let seeds_as_ranges = seeds.chunks(2)
+                            .filter(|x| x[1] > 0)
+                            .map(|x| Interval::with_length(x[0], x[1]))
+                            .collect();
+
+    return Ok(Almanac { seeds, seeds_as_ranges, maps });
+}
+
+pub fn parse(input: &str) -> REPR {
+    return try_parse(input).unwrap_or_else(|e| panic!("{}", e));
 }
 
 fn main() {
-    read_and_write(parse, &[compute_1, compute_2]);
+    match env::args().nth(1).as_deref() {
+        Some("--print-map") => print!("{}", parse(&read_input()).seed_to_location().unwrap_or_else(|e| fail(&e))),
+        Some("--lowest-seeds") => {
+            let (location, seeds) = parse(&read_input()).seeds_reaching_lowest_location().unwrap_or_else(|e| fail(&e));
+            println!("location {} is reached from seeds {}", location, seeds.iter().join(", "));
+        },
+        Some("--convert") => {
+            let args: Vec<String> = env::args().skip(2).collect();
+            let [from, to, value] = args.as_slice() else {
+                fail("usage: --convert <from> <to> <value>");
+            };
+            let value: u64 = value.parse().unwrap_or_else(|_| fail(&format!("invalid value {}", value)));
+            let almanac = parse(&read_input());
+            if let Some(cycle) = almanac.find_cycle() {
+                eprintln!("warning: conversion cycle {}", cycle.join(" -> "));
+            }
+            match almanac.compose(from, to) {
+                Ok(map) => println!("{}", map.get(value)),
+                Err(e) => eprintln!("{}", e),
+            }
+        },
+        Some("--lint") => {
+            for diagnostic in lint(&parse(&read_input())) {
+                println!("{}", diagnostic);
+            }
+        },
+        Some("--seeds-for") => {
+            let start: u64 = env::args().nth(2).unwrap().parse().unwrap();
+            let length: u64 = env::args().nth(3).map_or(1, |l| l.parse().unwrap());
+            let seeds = parse(&read_input()).seeds_for_locations(Interval::with_length(start, length))
+                .unwrap_or_else(|e| fail(&e));
+            println!("{}", seeds.iter().join(", "));
+        },
+        _ => {
+            let input = read_input();
+            for compute in [compute_1, compute_2] {
+                match compute(parse(&input)) {
+                    Ok(result) => println!("{}", result),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        },
+    }
 }
 
 #[cfg(test)]
@@ -283,56 +410,202 @@ mod tests {
 
     #[test]
     fn test_part1() {
+        let seed_to_location = parse(INPUT).seed_to_location().unwrap();
+
+        assert_eq!(seed_to_location.get(79), 82);
+        assert_eq!(seed_to_location.get(14), 43);
+        assert_eq!(seed_to_location.get(55), 86);
+        assert_eq!(seed_to_location.get(13), 35);
+
+        assert_eq!(compute_1(parse(INPUT)), Ok(35));
+    }
+
+    #[test]
+    fn test_map() {
         let almanac = parse(INPUT);
+        let map = almanac.conversions_from("seed").next().unwrap();
+        let start_seed = Interval::with_length(79, 1);
 
-        assert_eq!(almanac.convert_seed(79), 82);
-        assert_eq!(almanac.convert_seed(14), 43);
-        assert_eq!(almanac.convert_seed(55), 86);
-        assert_eq!(almanac.convert_seed(13), 35);
+        assert_eq!(map.to, "soil");
+        assert_eq!(map.interval_map().unwrap().map_interval(start_seed), vec![Interval::with_length(81, 1)]);
+    }
 
-        assert_eq!(compute_1(parse(INPUT)), 35);
+    #[test]
+    fn test_compose() {
+        let almanac = parse(INPUT);
+        let seed_to_location = almanac.seed_to_location().unwrap();
+        for seed in 0..120 {
+            let mut step = "seed";
+            let mut value = seed;
+            while let Some(conversion) = almanac.conversions_from(step).next() {
+                value = conversion.interval_map().unwrap().get(value);
+                step = &conversion.to;
+            }
+            assert_eq!(seed_to_location.get(seed), value, "seed {}", seed);
+        }
     }
 
     #[test]
-    fn test_split() {
-        let r1 = Range::new(1, 10);
-        let r2 = Range::new(2, 2);
-        let r3 = Range::new(1, 1);
-        let r4 = Range::new(4, 7);
-        let r5 = Range::new(37, 7);
-        assert_eq!(r1.split(r2), (None, Some(r2), None));
-        assert_eq!(r2.split(r1), (Some(r3), Some(r2), Some(r4)));
-        assert_eq!(r5.split(r1), (Some(r1), None, None));
-        assert_eq!(r1.split(r5), (None, None, Some(r5)));
+    fn test_graph() {
+        let almanac = parse(INPUT);
+        let path: Vec<&str> = almanac.path("soil", "humidity").unwrap().iter().map(|m| m.to.as_str()).collect();
+        assert_eq!(path, vec!["fertilizer", "water", "light", "temperature", "humidity"]);
+        assert!(almanac.path("seed", "seed").unwrap().is_empty());
+        // seed 79 is soil 81, soil 81 is humidity 78
+        assert_eq!(almanac.compose("soil", "humidity").unwrap().get(81), 78);
+
+        assert_eq!(almanac.path("location", "seed").err(), Some("no conversion path from location to seed".to_owned()));
+        assert_eq!(almanac.path("seed", "gold").err(), Some("unknown category gold".to_owned()));
+        assert_eq!(almanac.find_cycle(), None);
+
+        let cyclic = parse(indoc! {"
+            seeds: 1 2
+
+            seed-to-soil map:
+            5 1 2
+
+            soil-to-water map:
+            1 1 1
+
+            water-to-seed map:
+            1 5 1
+        "});
+        assert_eq!(cyclic.find_cycle(), Some(vec!["seed", "soil", "water", "seed"]));
+        assert_eq!(cyclic.compose("water", "soil").unwrap().get(5), 5);
+        assert_eq!(cyclic.compose("water", "soil").unwrap().get(6), 6);
+        assert_eq!(compute_1(cyclic), Err("unknown category location".to_owned()));
+
+        let far = parse(indoc! {"
+            seeds: 9223372036854775810 1
+
+            seed-to-location map:
+            9223372036854775807 9223372036854775808 5
+        "});
+        assert!(lint(&far).is_empty());
+        assert_eq!(compute_2(far), Ok(9223372036854775809));
+
+        let too_far = parse(indoc! {"
+            seeds: 1 2
+
+            seed-to-location map:
+            18446744073709551610 0 5
+        "});
+        assert_eq!(compute_1(too_far), Err("line 4: offset does not fit in i64".to_owned()));
     }
 
     #[test]
-    fn test_map() {
+    fn test_inverse() {
         let almanac = parse(INPUT);
-        let map = almanac.conversion_for("seed").unwrap();
-        let start_seed = Range::new(79, 1);
-        let result = map.map(TypeRange { step: "seed", ranges: vec![start_seed] });
+        assert_eq!(almanac.seeds_for_locations(Interval::with_length(35, 1)), Ok(vec![Interval::with_length(13, 1)]));
+        let seeds = almanac.seeds_for_locations(Interval::new(40, 50)).unwrap();
+        let seed_to_location = almanac.seed_to_location().unwrap();
+        for seed in 0..120 {
+            let reaches = (40..50).contains(&seed_to_location.get(seed));
+            assert_eq!(seeds.iter().any(|s| s.contains(seed)), reaches, "seed {}", seed);
+        }
+
+        assert_eq!(almanac.seeds_reaching_lowest_location(), Ok((46, vec![Interval::with_length(82, 1)])));
+    }
+
+    #[test]
+    fn test_lint() {
+        assert!(lint(&parse(INPUT)).is_empty());
+
+        let faulty = parse(indoc! {"
+            seeds: 1 2
+
+            seed-to-soil map:
+            10 0 5
+            20 3 5
+            30 30 2
+            0 40 0
+
+            soil-to-location map:
+            0 10 5
+            5 20 5
+            50 0 5
+        "});
+        let diagnostics: Vec<String> = lint(&faulty).iter().map(|d| d.to_string()).collect();
+        assert_eq!(diagnostics, vec![
+            "line 3: gap [8, 30) in seed-to-soil is not mapped",
+            "line 5: source [3, 5) overlaps line 4, which takes precedence",
+            "line 6: range maps onto itself",
+            "line 7: empty range",
+            "line 9: gap [5, 10) in soil-to-location is not mapped",
+            "line 9: gap [15, 20) in soil-to-location is not mapped",
+        ]);
+
+        let overlapping = parse(indoc! {"
+            seeds: 1 2
+
+            seed-to-soil map:
+            10 0 5
+            20 3 5
+        "});
+        assert_eq!(lint(&overlapping), vec![Diagnostic {
+            line: 5,
+            message: "source [3, 5) overlaps line 4, which takes precedence".to_owned(),
+        }]);
+
+        let with_no_op = parse(indoc! {"
+            seeds: 1 2
+
+            seed-to-soil map:
+            10 0 5
+            20 3 5
+            30 30 2
+        "});
+        let diagnostics: Vec<String> = lint(&with_no_op).iter().map(|d| d.to_string()).collect();
+        assert_eq!(diagnostics, vec![
+            "line 3: gap [8, 30) in seed-to-soil is not mapped",
+            "line 5: source [3, 5) overlaps line 4, which takes precedence",
+            "line 6: range maps onto itself",
+        ]);
+    }
 
-        assert_eq!(result.step, "soil");
-        assert_eq!(result.ranges, vec![Range::new(81, 1)]);
+    #[test]
+    fn test_tolerant_parse() {
+        let messy = "seeds: 79 14  55 13 \r\n\r\n\r\n"
+            .to_owned()
+            + &INPUT.split("\n\n").skip(1).collect::<Vec<_>>().into_iter().rev().join("\n\n\n")
+                .replace("\n", "  \r\n");
+        let almanac = try_parse(&messy).unwrap();
+        assert_eq!(almanac.seeds, vec![79, 14, 55, 13]);
+        assert_eq!(almanac.maps[0].from, "humidity");
+        assert_eq!(compute_1(try_parse(&messy).unwrap()), Ok(35));
+        assert_eq!(compute_2(try_parse(&messy).unwrap()), Ok(46));
+        assert_eq!(compute_1(parse(INPUT.trim_end())), Ok(35));
+
+        assert_eq!(try_parse("\n").err(), Some(ParseError::MissingSeeds));
+        assert_eq!(try_parse("seeds: 1 2 3").err(), Some(ParseError::OddSeedCount(3)));
+        assert_eq!(try_parse("seeds: 1 2\n\n1 2 3\n").err(), Some(ParseError::RangeWithoutMap { line: 3 }));
+        assert_eq!(try_parse("seeds: 1 2\nseed-to-soil map:\n1 2 x\n").err(),
+            Some(ParseError::InvalidLine { line: 3, content: "1 2 x".to_owned() }));
+        assert_eq!(try_parse("\nseeds: 18446744073709551615 5").err(), Some(ParseError::RangeOverflow { line: 2 }));
+        assert!(try_parse("seeds: 18446744073709551615 0").is_ok());
+        assert_eq!(try_parse("seeds: 1 2\nseed-to-soil map:\n0 18446744073709551610 10\n").err(),
+            Some(ParseError::RangeOverflow { line: 3 }));
+        assert_eq!(try_parse("seeds: 1 2\nseed-to-soil map:\n18446744073709551610 0 10\n").err(),
+            Some(ParseError::RangeOverflow { line: 3 }));
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(compute_2(parse(INPUT)), 46);
+        assert_eq!(compute_2(parse(INPUT)), Ok(46));
     }
 }
 
-fn read_and_write<T, S: Display>(parse: fn (&str) -> T, compute: &[fn(T) -> S] ) {
+fn read_input() -> String {
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
+    return input;
+}
 
-    for f in compute {
-        let result = f(parse(&input));
-        println!("{}", result);    
-    }
-}
\ No newline at end of file
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
@@ -1,75 +1,233 @@
+use std::env;
 use std::io;
 use std::io::Read;
-use std::fmt::Display;
 use std::iter::zip;
+use std::process;
 
-type REPR = Vec<Race>;
+use itertools::Itertools;
+use num::{BigInt, Integer, Signed, Zero};
+
+type REPR = Races;
+
+pub struct Races {
+    races: Vec<Race>,
+    // the single race read by ignoring the spaces between the numbers
+    kerned: Result<Race, String>,
+}
 
 pub struct Race {
     time: u64,
     distance: u64,
 }
 
-impl Race {
-    fn win_points(&self) -> (u64, u64) {
-        let time = self.time as f64;
-        let distance = self.distance as f64;
-        //find x for which
-        //x * (time - x) > distance
-        //-x^2 + x*time - distance > 0
-        //determinant: time^2 - 4*(-1)*(-distance)
-        //solutions: (-time +/- sqrt(determinant))/(2 * (-1))
-        let determinant = (time * time - 4.0 * distance) as f64;
-        let sol_a = (-time + determinant.sqrt()) / (-2.0);
-        let sol_b = (-time - determinant.sqrt()) / (-2.0);
-
-        // sol_a and sol_b are the exact zero points,
-        // so the time further to the extremes is too slow,
-        // so round towards center point
-        let sol_from = sol_a.ceil() as u64;
-        let sol_to = sol_b.floor() as u64;
-
-        // however we need to be strictly greater to beat the record,
-        // so test whether we are at zero point & add if needed
-        let from = if sol_from * (self.time - sol_from) == self.distance {sol_from + 1} else {sol_from};
-        let to = if sol_to * (self.time - sol_to) == self.distance {sol_to - 1} else {sol_to};
-        return (from, to);
+#[derive(Clone, Copy, Debug)]
+pub struct RaceModel {
+    // speed gained per millisecond the button is held
+    acceleration: u64,
+    initial_speed: u64,
+    max_speed: Option<u64>,
+    // the button can only be held for a multiple of this many milliseconds
+    granularity: u64,
+}
+
+impl Default for RaceModel {
+    fn default() -> Self {
+        RaceModel { acceleration: 1, initial_speed: 0, max_speed: None, granularity: 1 }
+    }
+}
+
+impl RaceModel {
+    fn travelled(&self, race: &Race, hold: u64) -> u128 {
+        // saturating is fine: anything beyond u64 beats every record
+        let speed = (self.initial_speed as u128).saturating_add(self.acceleration as u128 * hold as u128);
+        let speed = self.max_speed.map_or(speed, |max| speed.min(max as u128));
+        return speed.saturating_mul((race.time - hold) as u128);
+    }
+
+    fn win_points(&self, race: &Race) -> Option<(u64, u64)> {
+        assert!(self.granularity > 0, "granularity must be positive");
+        let (from, to) = self.win_steps_analytic(race).unwrap_or_else(|| self.win_steps_search(race))?;
+        return Some((from * self.granularity, to * self.granularity));
     }
 
+    fn ways_to_win(&self, race: &Race) -> u64 {
+        return self.win_points(race).map_or(0, |(from, to)| (to - from) / self.granularity + 1);
+    }
+
+    fn wins(&self, race: &Race, step: u64) -> bool {
+        return self.travelled(race, step * self.granularity) > race.distance as u128;
+    }
+
+    // closed form for an uncapped speed, None if that does not apply
+    fn win_steps_analytic(&self, race: &Race) -> Option<Option<(u64, u64)>> {
+        if self.max_speed.is_some() || self.acceleration == 0 {
+            return None;
+        }
+        let steps = race.time / self.granularity;
+        // the coefficients outgrow i128 for large times, so the closed form works on big integers
+        let g = BigInt::from(self.granularity);
+        let a = BigInt::from(self.acceleration) * &g;
+        let v = BigInt::from(self.initial_speed);
+        let t = BigInt::from(race.time);
+        //find step k, holding for x = g*k, for which
+        //(v + a*k) * (t - g*k) > distance
+        //-a*g*k^2 + (a*t - v*g)*k + v*t - distance > 0
+        //determinant: (a*t - v*g)^2 + 4*a*g*(v*t - distance)
+        //solutions: ((a*t - v*g) +/- sqrt(determinant)) / (2*a*g)
+        let q = &a * &g;
+        let b = &a * &t - &v * &g;
+        let c = &v * &t - BigInt::from(race.distance);
+        let determinant: BigInt = &b * &b + &q * 4 * &c;
+        if determinant.is_negative() {
+            return Some(None);
+        }
+        let root = determinant.sqrt();
+        let two_q = &q * 2;
+        let clamp = |k: BigInt| -> u64 { k.clamp(BigInt::zero(), BigInt::from(steps)).try_into().unwrap() };
+
+        // the top of the parabola, one of the steps around its real vertex
+        let vertex = clamp(b.div_floor(&two_q));
+        let peak = if vertex < steps && self.travelled(race, (vertex + 1) * self.granularity) > self.travelled(race, vertex * self.granularity) {
+            vertex + 1
+        } else {
+            vertex
+        };
+        if !self.wins(race, peak) {
+            return Some(None);
+        }
+
+        // the integer square root rounds down, so the zero points can be a step off
+        let mut from = clamp((&b - &root).div_floor(&two_q)).min(peak);
+        while from > 0 && self.wins(race, from - 1) {
+            from -= 1;
+        }
+        while !self.wins(race, from) {
+            from += 1;
+        }
+        let mut to = clamp((&b + &root).div_floor(&two_q)).max(peak);
+        while to < steps && self.wins(race, to + 1) {
+            to += 1;
+        }
+        while !self.wins(race, to) {
+            to -= 1;
+        }
+        return Some(Some((from, to)));
+    }
+
+    // the distance first rises and then falls with the hold time, so binary search works
+    fn win_steps_search(&self, race: &Race) -> Option<(u64, u64)> {
+        let steps = race.time / self.granularity;
+        let travelled = |k: u64| self.travelled(race, k * self.granularity);
+
+        let (mut low, mut high) = (0, steps);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if travelled(mid) < travelled(mid + 1) { low = mid + 1 } else { high = mid }
+        }
+        let peak = low;
+        if !self.wins(race, peak) {
+            return None;
+        }
+
+        let (mut low, mut high) = (0, peak);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.wins(race, mid) { high = mid } else { low = mid + 1 }
+        }
+        let from = low;
+
+        let (mut low, mut high) = (peak, steps);
+        while low < high {
+            let mid = high - (high - low) / 2;
+            if self.wins(race, mid) { low = mid } else { high = mid - 1 }
+        }
+        return Some((from, low));
+    }
 }
 
 pub fn compute_1(input: REPR) -> u64 {
-    return input.iter()
-        .map(|r| r.win_points())
-        .map(|(from, to)| to - from + 1)
+    return compute_1_with(input, &RaceModel::default());
+}
+
+pub fn compute_1_with(input: REPR, model: &RaceModel) -> u64 {
+    return input.races.iter()
+        .map(|r| model.ways_to_win(r))
         .product();
 }
 
-pub fn compute_2(input: REPR) -> u64 {
-    let race = input.into_iter().reduce(|acc, e| {
-        Race {
-            time: append(acc.time, e.time),
-            distance: append(acc.distance, e.distance),
-        }
-    }).unwrap();
-    let (from, to) = race.win_points();
-    return to - from + 1;
+pub fn compute_2(input: REPR) -> Result<u64, String> {
+    return compute_2_with(input, &RaceModel::default());
 }
 
-pub fn append(a: u64, b: u64) -> u64 {
-    let num_digits_b = ((b as f64).log10() + 1.0) as u32;
-    return a * 10_u64.pow(num_digits_b) + b;
+pub fn compute_2_with(input: REPR, model: &RaceModel) -> Result<u64, String> {
+    return Ok(model.ways_to_win(&input.kerned?));
+}
+
+fn kern(numbers: &[&str]) -> Result<u64, String> {
+    let digits = numbers.concat();
+    return digits.chars()
+        .try_fold(0_u64, |acc, c| {
+            let digit = c.to_digit(10).ok_or(format!("{} is not a number", digits))?;
+            acc.checked_mul(10)
+                .and_then(|n| n.checked_add(digit as u64))
+                .ok_or(format!("{} does not fit in u64", digits))
+        });
 }
 
 pub fn parse(input: &str) -> REPR {
-    let times: Vec<u64> = input.lines().nth(0).unwrap().split_whitespace().skip(1).map(|s| s.parse().unwrap()).collect();
-    let distance: Vec<u64> = input.lines().nth(1).unwrap().split_whitespace().skip(1).map(|s| s.parse().unwrap()).collect();
+    let mut lines = input.lines();
+    let times: Vec<&str> = lines.next().unwrap().split_whitespace().skip(1).collect();
+    let distances: Vec<&str> = lines.next().unwrap().split_whitespace().skip(1).collect();
+
+    let races = zip(&times, &distances)
+        .map(|(time, distance)| Race{time: time.parse().unwrap(), distance: distance.parse().unwrap()})
+        .collect();
+    let kerned = kern(&times)
+        .and_then(|time| Ok(Race{time, distance: kern(&distances)?}));
 
-    return zip(times, distance).map(|(time, distance)| Race{time, distance}).collect();
+    return Races { races, kerned };
+}
+
+pub fn parse_model(args: &[String]) -> Result<RaceModel, String> {
+    let mut model = RaceModel::default();
+    for (option, value) in args.iter().tuples() {
+        let value: u64 = value.parse().map_err(|_| format!("invalid value {} for {}", value, option))?;
+        match option.as_str() {
+            "--acceleration" => model.acceleration = value,
+            "--initial-speed" => model.initial_speed = value,
+            "--max-speed" => model.max_speed = Some(value),
+            "--granularity" if value == 0 => return Err("granularity must be positive".to_owned()),
+            "--granularity" => model.granularity = value,
+            _ => return Err(format!("unknown option {} {}", option, value)),
+        }
+    }
+    if !args.len().is_multiple_of(2) {
+        return Err(format!("missing value for {}", args.last().unwrap()));
+    }
+    return Ok(model);
 }
 
 fn main() {
-    read_and_write(parse, &[compute_1, compute_2]);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let model = match parse_model(&args) {
+        Ok(model) => model,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        },
+    };
+
+    let mut input = String::new();
+
+    io::stdin()
+        .read_to_string(&mut input)
+        .expect("Failed to read input");
+
+    println!("{}", compute_1_with(parse(&input), &model));
+    match compute_2_with(parse(&input), &model) {
+        Ok(result) => println!("{}", result),
+        Err(e) => eprintln!("{}", e),
+    }
 }
 
 #[cfg(test)]
@@ -84,10 +242,88 @@ mod tests {
 
     #[test]
     fn test_win_points() {
-        assert_eq!(Race{time: 7, distance: 9}.win_points(), (2, 5));
-        assert_eq!(Race{time: 15, distance: 40}.win_points(), (4, 11));
-        assert_eq!(Race{time: 30, distance: 200}.win_points(), (11, 19));
-        assert_eq!(Race{time: 71530, distance: 940200}.win_points(), (14, 71516));
+        let model = RaceModel::default();
+        assert_eq!(model.win_points(&Race{time: 7, distance: 9}), Some((2, 5)));
+        assert_eq!(model.win_points(&Race{time: 15, distance: 40}), Some((4, 11)));
+        assert_eq!(model.win_points(&Race{time: 30, distance: 200}), Some((11, 19)));
+        assert_eq!(model.win_points(&Race{time: 71530, distance: 940200}), Some((14, 71516)));
+        assert_eq!(model.win_points(&Race{time: 4, distance: 4}), None);
+        assert_eq!(model.win_points(&Race{time: 4, distance: 3}), Some((2, 2)));
+        assert_eq!(model.win_points(&Race{time: 0, distance: 0}), None);
+        assert_eq!(model.win_points(&Race{time: 3, distance: 0}), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_win_points_extreme() {
+        let model = RaceModel::default();
+        let travelled = |x: u64, time: u64| x as u128 * (time - x) as u128;
+        let races = [
+            Race{time: u64::MAX, distance: u64::MAX},
+            Race{time: u64::MAX, distance: 0},
+            Race{time: 1 << 60, distance: (1 << 62) - 1},
+            Race{time: (1 << 53) + 1, distance: 7 * (1 << 53) + 3},
+            Race{time: 1 << 33, distance: u64::MAX},
+            Race{time: (1 << 33) - 1, distance: u64::MAX},
+        ];
+        for race in races {
+            // the closed form covers every u64 time, without falling back to the search
+            assert_eq!(model.win_steps_analytic(&race), Some(model.win_steps_search(&race)));
+            let distance = race.distance as u128;
+            match model.win_points(&race) {
+                Some((from, to)) => {
+                    assert!(travelled(from, race.time) > distance);
+                    assert!(travelled(from - 1, race.time) <= distance);
+                    assert!(travelled(to, race.time) > distance);
+                    assert!(travelled(to + 1, race.time) <= distance);
+                },
+                None => assert!(travelled(race.time / 2, race.time) <= distance),
+            }
+        }
+        // a record of exactly (time / 2)^2 can only be tied, never beaten
+        let half = 1_u64 << 31;
+        assert_eq!(model.win_points(&Race{time: 2 * half, distance: half * half}), None);
+        assert_eq!(model.win_points(&Race{time: 2 * half, distance: half * half - 1}), Some((half, half)));
+
+        let steep = RaceModel { acceleration: u64::MAX, initial_speed: u64::MAX, max_speed: None, granularity: 3 };
+        let race = Race{time: u64::MAX, distance: u64::MAX};
+        assert_eq!(steep.win_steps_analytic(&race), Some(steep.win_steps_search(&race)));
+    }
+
+    #[test]
+    fn test_race_model() {
+        let brute_force = |model: &RaceModel, race: &Race| -> Option<(u64, u64)> {
+            let winning: Vec<u64> = (0..=race.time)
+                .step_by(model.granularity as usize)
+                .filter(|hold| model.travelled(race, *hold) > race.distance as u128)
+                .collect();
+            Some((*winning.first()?, *winning.last()?))
+        };
+        for acceleration in 0..4 {
+            for initial_speed in 0..4 {
+                for max_speed in [None, Some(1), Some(3), Some(8)] {
+                    for granularity in 1..4 {
+                        let model = RaceModel { acceleration, initial_speed, max_speed, granularity };
+                        for time in 0..25 {
+                            for distance in (0..80).step_by(3) {
+                                let race = Race { time, distance };
+                                assert_eq!(model.win_points(&race), brute_force(&model, &race), "{:?} {} {}", model, time, distance);
+                                assert_eq!(model.win_steps_search(&race), model.win_points(&race).map(|(f, t)| (f / granularity, t / granularity)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_model() {
+        let args = ["--max-speed", "20", "--granularity", "2"].map(String::from);
+        let model = parse_model(&args).unwrap();
+        assert_eq!((model.acceleration, model.max_speed, model.granularity), (1, Some(20), 2));
+        assert_eq!(compute_1_with(parse(INPUT), &model), 2 * 4 * 4);
+        assert_eq!(parse_model(&["--granularity", "0"].map(String::from)).err(), Some("granularity must be positive".to_owned()));
+        assert!(parse_model(&["--acceleration"].map(String::from)).is_err());
     }
 
     #[test]
@@ -97,20 +333,21 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        assert_eq!(compute_2(parse(INPUT)), 71503);
+        assert_eq!(compute_2(parse(INPUT)), Ok(71503));
     }
-}
-
-fn read_and_write<T, S: Display>(parse: fn (&str) -> T, compute: &[fn(T) -> S] ) {
-    let mut input = String::new();
-
-    io::stdin()
-        .read_to_string(&mut input)
-        .expect("Failed to read input");
 
+    #[test]
+    fn test_kerning() {
+        let races = parse(indoc! {"
+            Time:      7  0   30
+            Distance:  9  100  1000
+        "});
+        let kerned = races.kerned.unwrap();
+        assert_eq!((kerned.time, kerned.distance), (7030, 91001000));
 
-    for f in compute {
-        let result = f(parse(&input));
-        println!("{}", result);    
+        assert_eq!(kern(&["1844674407370955161", "5"]), Ok(u64::MAX));
+        assert_eq!(kern(&["1844674407370955161", "6"]), Err("18446744073709551616 does not fit in u64".to_owned()));
+        assert_eq!(compute_2(parse("Time: 99999999999 99999999999\nDistance: 1 2\n")),
+            Err("9999999999999999999999 does not fit in u64".to_owned()));
     }
-}
\ No newline at end of file
+}
@@ -1,74 +1,243 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::env;
 use std::io;
 use std::io::Read;
+use std::process;
 use std::fmt;
 
 use itertools::Itertools;
 
 type REPR = Vec<(Hand, u64)>;
 
-#[derive(PartialEq, Eq, Debug)]
-pub struct Hand {
-    cards: Vec<Card>,
-    hand_type: HandType,
-}
+pub trait Rules {
+    // card labels from weakest to strongest
+    fn card_order(&self) -> &str;
 
-impl Hand {
-    pub fn new(cards_str: &str, with_joker: bool) -> Hand {
-        let cards = cards_str.chars().map(|c| Card(c, with_joker)).collect();
-        let hand_type = Self::calc_hand_type(&cards, with_joker);
-        Hand {
-            cards,
-            hand_type
-        }
+    fn wildcards(&self) -> &str {
+        ""
+    }
+
+    // when not empty, every card is written as its label followed by its suit
+    fn suits(&self) -> &str {
+        ""
+    }
+
+    fn straights(&self) -> bool {
+        false
     }
 
-    fn calc_hand_type(cards: &Vec<Card>, with_joker: bool) -> HandType {
-        let mut counts_per_card: HashMap<&Card, usize> = cards.iter().counts();
+    fn strength(&self, label: char) -> u64 {
+        assert!(self.card_order().len() <= MAX_LABELS, "at most {} different cards are supported", MAX_LABELS);
+        self.card_order().find(label)
+            .unwrap_or_else(|| panic!("unknown card {}", label)) as u64
+    }
 
-        let number_jokers = if with_joker {
-            counts_per_card.remove(&Card('J', true)).unwrap_or(0)
+    fn hand_type(&self, cards: &[Card]) -> HandType {
+        let kind = of_a_kind(cards, self.wildcards());
+        let needed = sequence_length(cards.len());
+        let strongest = self.card_order().len() as u64 - 1;
+        let straight = self.straights() && is_straight(cards.iter(), strongest, needed);
+        let flush_suits = if self.suits().is_empty() { Vec::new() } else { flush_suits(cards, needed) };
+        // the straight has to be made of the cards of one flush suit
+        let straight_flush = self.straights() && flush_suits.iter()
+            .any(|suit| is_straight(cards.iter().filter(|c| c.suit == *suit), strongest, needed));
+        let sequence = if straight_flush {
+            HandType::StraightFlush
+        } else if !flush_suits.is_empty() {
+            HandType::Flush
+        } else if straight {
+            HandType::Straight
         } else {
-            0
+            HandType::HighCard
         };
-        
-        let mut counts: Vec<_> = counts_per_card.values().sorted().rev().copied().collect();
-        if counts.len() == 0 {
-            counts.push(number_jokers);
+        return kind.max(sequence);
+    }
+}
+
+fn of_a_kind(cards: &[Card], wildcards: &str) -> HandType {
+    // counting by strength avoids hashing the labels
+    let mut counts_per_card = [0_usize; MAX_LABELS];
+    let mut number_jokers = 0;
+    for c in cards {
+        if wildcards.contains(c.label) {
+            number_jokers += 1;
         } else {
-            counts[0] += number_jokers;
+            counts_per_card[c.strength as usize] += 1;
         }
+    }
 
-        if counts.len() == 5 {
-            return HandType::HighCard;
-        }
-        if counts.len() == 4 {
-            return HandType::OnePair;
-        }
-        if counts.len() == 3 && counts[0] == 2 {
-            return HandType::TwoPair;
+    let (mut first, mut second) = (0, 0);
+    for count in counts_per_card {
+        if count > first {
+            second = first;
+            first = count;
+        } else if count > second {
+            second = count;
         }
-        if counts.len() == 3 && counts[0] == 3 {
-            return HandType::ThreeOfAKind;
-        }
-        if counts.len() == 2 && counts[0] == 3 {
-            return HandType::FullHouse;
+    }
+    first += number_jokers;
+
+    return match (first, second) {
+        (5.., _) => HandType::FiveOfAKind,
+        (4, _) => HandType::FourOfAKind,
+        (3, 2..) => HandType::FullHouse,
+        (3, _) => HandType::ThreeOfAKind,
+        (2, 2) => HandType::TwoPair,
+        (2, _) => HandType::OnePair,
+        _ => HandType::HighCard,
+    };
+}
+
+// straights and flushes are made of five cards of the hand, or of the whole hand when it is
+// shorter; hands of less than three cards have neither
+fn sequence_length(hand_size: usize) -> usize {
+    return if hand_size < 3 { usize::MAX } else { hand_size.min(5) };
+}
+
+// `needed` distinct cards in a row, the strongest card can also be the lowest
+fn is_straight<'a>(cards: impl Iterator<Item = &'a Card>, strongest: u64, needed: usize) -> bool {
+    let mut strengths: Vec<i64> = cards.map(|c| c.strength as i64).sorted().dedup().collect();
+    if strengths.last() == Some(&(strongest as i64)) {
+        strengths.insert(0, -1);
+    }
+    let mut run = 1;
+    for (a, b) in strengths.iter().tuple_windows() {
+        run = if b - a == 1 { run + 1 } else { 1 };
+        if run >= needed {
+            return true;
         }
-        if counts.len() == 2 && counts[0] == 4 {
-            return HandType::FourOfAKind;
+    }
+    return false;
+}
+
+// the suits with at least `needed` cards
+fn flush_suits(cards: &[Card], needed: usize) -> Vec<Option<char>> {
+    return cards.iter()
+        .map(|c| c.suit)
+        .counts()
+        .into_iter()
+        .filter(|(_, count)| *count >= needed)
+        .map(|(suit, _)| suit)
+        .collect();
+}
+
+pub struct CamelCards;
+
+impl Rules for CamelCards {
+    fn card_order(&self) -> &str {
+        "23456789TJQKA"
+    }
+}
+
+pub struct Jokers;
+
+impl Rules for Jokers {
+    fn card_order(&self) -> &str {
+        "J23456789TQKA"
+    }
+
+    fn wildcards(&self) -> &str {
+        "J"
+    }
+}
+
+pub struct CustomRules {
+    card_order: String,
+    wildcards: String,
+}
+
+impl Rules for CustomRules {
+    fn card_order(&self) -> &str {
+        &self.card_order
+    }
+
+    fn wildcards(&self) -> &str {
+        &self.wildcards
+    }
+}
+
+pub struct SuitlessPoker;
+
+impl Rules for SuitlessPoker {
+    fn card_order(&self) -> &str {
+        "23456789TJQKA"
+    }
+
+    fn straights(&self) -> bool {
+        true
+    }
+}
+
+// cards are written like "Ah" or "Ts"
+pub struct Poker;
+
+impl Rules for Poker {
+    fn card_order(&self) -> &str {
+        "23456789TJQKA"
+    }
+
+    fn suits(&self) -> &str {
+        "cdhs"
+    }
+
+    fn straights(&self) -> bool {
+        true
+    }
+}
+
+// a card is encoded as its strength + 1 in the sort key, leaving 0 for "no card"
+const BITS_PER_CARD: u32 = 6;
+const MAX_LABELS: usize = (1 << BITS_PER_CARD) - 1;
+const TYPE_BITS: u32 = 4;
+const KEY_CARDS: usize = ((u128::BITS - TYPE_BITS) / BITS_PER_CARD) as usize;
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Hand {
+    cards: Vec<Card>,
+    hand_type: HandType,
+    // type in the high bits, then the card strengths from the first card on,
+    // so comparing keys compares hands
+    key: u128,
+}
+
+impl Hand {
+    pub fn new(cards_str: &str, rules: &dyn Rules) -> Hand {
+        let chars: Vec<char> = cards_str.chars().collect();
+        let cards: Vec<Card> = if rules.suits().is_empty() {
+            chars.iter().map(|&label| Card { label, suit: None, strength: rules.strength(label) }).collect()
+        } else {
+            chars.chunks(2)
+                .map(|c| {
+                    assert!(c.len() == 2 && rules.suits().contains(c[1]), "invalid card {:?} in {}", c, cards_str);
+                    Card { label: c[0], suit: Some(c[1]), strength: rules.strength(c[0]) }
+                })
+                .collect()
+        };
+        let hand_type = rules.hand_type(&cards);
+        let key = Self::sort_key(&hand_type, &cards);
+        Hand {
+            cards,
+            hand_type,
+            key,
         }
-        if counts.len() == 1 {
-            return HandType::FiveOfAKind;
+    }
+
+    fn sort_key(hand_type: &HandType, cards: &[Card]) -> u128 {
+        let mut key = (*hand_type as u128) << (u128::BITS - TYPE_BITS);
+        for (idx, card) in cards.iter().take(KEY_CARDS).enumerate() {
+            let shift = u128::BITS - TYPE_BITS - BITS_PER_CARD * (idx as u32 + 1);
+            key |= (card.strength as u128 + 1) << shift;
         }
-        panic!("Unknown hand type for hand {:?} and counts {:?}", cards, &counts);
+        return key;
     }
 }
 
 impl Ord for Hand {
+    // only hands longer than the key (or differing in suits) need the cards to break a tie
     fn cmp(&self, other: &Self) -> Ordering {
-        self.hand_type.cmp(&other.hand_type)
-            .then(self.cards.cmp(&other.cards))
+        self.key.cmp(&other.key)
+            .then_with(|| self.cards.cmp(&other.cards))
     }
 }
 
@@ -80,30 +249,26 @@ impl PartialOrd for Hand {
 
 impl fmt::Display for Hand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let hand_str = self.cards.iter().map(|c| c.0).join("");
+        let hand_str = self.cards.iter().map(|c| format!("{}{}", c.label, c.suit.map_or(String::new(), String::from))).join("");
         write!(f, "({}, {:?})", hand_str, self.hand_type)
     }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
-enum HandType {HighCard, OnePair, TwoPair, ThreeOfAKind, FullHouse, FourOfAKind, FiveOfAKind}
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum HandType {HighCard, OnePair, TwoPair, ThreeOfAKind, Straight, Flush, FullHouse, FourOfAKind, StraightFlush, FiveOfAKind}
 
 #[derive(PartialEq, Eq, Hash, Debug)]
-struct Card(char, bool);
-
-impl Card {
-    pub fn strength(&self) -> u64 {
-        if self.1 {
-            "J23456789TQKA".find(self.0).unwrap() as u64
-        } else {
-            "23456789TJQKA".find(self.0).unwrap() as u64
-        }
-    }
+pub struct Card {
+    label: char,
+    suit: Option<char>,
+    strength: u64,
 }
 
 impl Ord for Card {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.strength().cmp(&other.strength())
+        self.strength.cmp(&other.strength)
+            .then(self.label.cmp(&other.label))
+            .then(self.suit.cmp(&other.suit))
     }
 }
 
@@ -122,27 +287,259 @@ pub fn compute(mut input: REPR) -> u64 {
             .sum()
 }
 
-pub fn parse1(input: &str) -> REPR {
+impl Hand {
+    // the hand with every wildcard replaced by the card it stands in for
+    fn substitution(&self, rules: &dyn Rules) -> Option<String> {
+        let wildcards = rules.wildcards();
+        if !self.cards.iter().any(|c| wildcards.contains(c.label)) {
+            return None;
+        }
+        let best = self.cards.iter()
+            .filter(|c| !wildcards.contains(c.label))
+            .map(|c| c.label)
+            .counts()
+            .into_iter()
+            .max_by_key(|(label, count)| (*count, rules.strength(*label)))
+            .map_or_else(|| rules.card_order().chars().rev().find(|c| !wildcards.contains(*c)).unwrap(), |(label, _)| label);
+        return Some(self.cards.iter()
+            .map(|c| {
+                let label = if wildcards.contains(c.label) { best } else { c.label };
+                format!("{}{}", label, c.suit.map_or(String::new(), String::from))
+            })
+            .join(""));
+    }
+
+    // why `self` ranks above `weaker`
+    fn decider(&self, weaker: &Hand) -> String {
+        if self.hand_type != weaker.hand_type {
+            return format!("hand type {:?} > {:?}", self.hand_type, weaker.hand_type);
+        }
+        // suits only order hands that are equal otherwise, so they are no decider
+        if let Some((idx, (a, b))) = self.cards.iter().zip(&weaker.cards).enumerate().find(|(_, (a, b))| a.strength != b.strength) {
+            return format!("card {}: {} > {}", idx + 1, a.label, b.label);
+        }
+        if self.cards.len() != weaker.cards.len() {
+            return format!("length {} > {}", self.cards.len(), weaker.cards.len());
+        }
+        return "tie".to_owned();
+    }
+}
+
+pub fn explain(mut input: REPR, rules: &dyn Rules) -> String {
+    input.sort_by(|(hand_a, _), (hand_b, _)| hand_a.cmp(hand_b));
+    let mut result = String::new();
+    for (idx, (hand, bid)) in input.iter().enumerate() {
+        let rank = idx as u64 + 1;
+        result += &format!("rank {}: {}", rank, hand);
+        if let Some(substitution) = hand.substitution(rules) {
+            result += &format!(" as {}", substitution);
+        }
+        result += &format!(" bid {} wins {}", bid, rank * bid);
+        if idx > 0 {
+            result += &format!(", beats rank {} on {}", rank - 1, hand.decider(&input[idx - 1].0));
+        }
+        result += "\n";
+    }
+    return result;
+}
+
+// xorshift64*, good enough for dealing cards and reproducible from a seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // the state must never be zero, xorshift would stay there forever
+        let state = seed ^ 0x9E3779B97F4A7C15;
+        Rng(if state == 0 { 0x9E3779B97F4A7C15 } else { state })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        return self.0.wrapping_mul(0x2545F4914F6CDD1D);
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        return (self.next_u64() % n as u64) as usize;
+    }
+}
+
+pub struct Deck {
+    cards: Vec<char>,
+}
+
+impl Deck {
+    pub fn new(labels: &str, copies: usize) -> Deck {
+        Deck { cards: labels.chars().flat_map(|c| std::iter::repeat_n(c, copies)).collect() }
+    }
+
+    pub fn deal(&self, rng: &mut Rng, players: usize, hand_size: usize) -> Result<Vec<String>, String> {
+        if players.checked_mul(hand_size).is_none_or(|n| n > self.cards.len()) {
+            return Err(format!("{} cards are not enough to deal {} hands of {}", self.cards.len(), players, hand_size));
+        }
+        let mut cards = self.cards.clone();
+        // partial Fisher-Yates: only shuffle the cards that get dealt
+        for i in 0..players * hand_size {
+            let j = i + rng.below(cards.len() - i);
+            cards.swap(i, j);
+        }
+        return Ok(cards[..players * hand_size].chunks(hand_size).map(|c| c.iter().collect()).collect());
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct TypeStats {
+    dealt: u64,
+    wins: u64,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct SimulationReport {
+    hands: u64,
+    per_type: BTreeMap<HandType, TypeStats>,
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<14} {:>10} {:>9} {:>9}", "type", "dealt", "share", "win rate")?;
+        for (hand_type, stats) in &self.per_type {
+            writeln!(f, "{:<14} {:>10} {:>8.3}% {:>8.3}%",
+                format!("{:?}", hand_type),
+                stats.dealt,
+                100.0 * stats.dealt as f64 / self.hands as f64,
+                100.0 * stats.wins as f64 / stats.dealt as f64)?;
+        }
+        Ok(())
+    }
+}
+
+// every round deals one hand per player, the best hand wins (identical best hands all win)
+pub fn simulate(deck: &Deck, rules: &dyn Rules, players: usize, hand_size: usize, rounds: usize, seed: u64) -> Result<SimulationReport, String> {
+    if players == 0 || hand_size == 0 || rounds == 0 {
+        return Err("players, hand size and rounds must be positive".to_owned());
+    }
+    let mut rng = Rng::new(seed);
+    let mut report = SimulationReport::default();
+    for _ in 0..rounds {
+        let hands: Vec<Hand> = deck.deal(&mut rng, players, hand_size)?.iter()
+            .map(|cards| Hand::new(cards, rules))
+            .collect();
+        let best = hands.iter().max().unwrap();
+        for hand in &hands {
+            let stats = report.per_type.entry(hand.hand_type).or_default();
+            stats.dealt += 1;
+            if hand == best {
+                stats.wins += 1;
+            }
+        }
+        report.hands += players as u64;
+    }
+    return Ok(report);
+}
+
+pub struct SimulationOptions {
+    labels: String,
+    copies: usize,
+    players: usize,
+    hand_size: usize,
+    rounds: usize,
+    seed: u64,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        SimulationOptions { labels: CamelCards.card_order().to_owned(), copies: 4, players: 4, hand_size: 5, rounds: 100_000, seed: 0 }
+    }
+}
+
+impl SimulationOptions {
+    fn deck(&self) -> Deck {
+        return Deck::new(&self.labels, self.copies);
+    }
+
+    // the labels in the given order
+    fn rules(&self) -> CustomRules {
+        return CustomRules { card_order: self.labels.clone(), wildcards: String::new() };
+    }
+
+    // J turns into the weakest card, if the deck has one
+    fn joker_rules(&self) -> Option<CustomRules> {
+        if !self.labels.contains('J') {
+            return None;
+        }
+        let card_order = format!("J{}", self.labels.replace('J', ""));
+        return Some(CustomRules { card_order, wildcards: "J".to_owned() });
+    }
+}
+
+pub fn parse_simulation(args: &[String]) -> Result<SimulationOptions, String> {
+    let mut options = SimulationOptions::default();
+    for (option, value) in args.iter().tuples() {
+        let number = || value.parse::<usize>().map_err(|_| format!("invalid value {} for {}", value, option));
+        match option.as_str() {
+            "--labels" => options.labels = value.clone(),
+            "--copies" => options.copies = number()?,
+            "--players" => options.players = number()?,
+            "--hand-size" => options.hand_size = number()?,
+            "--rounds" => options.rounds = number()?,
+            "--seed" => options.seed = value.parse().map_err(|_| format!("invalid value {} for {}", value, option))?,
+            _ => return Err(format!("unknown option {}", option)),
+        }
+    }
+    if !args.len().is_multiple_of(2) {
+        return Err(format!("missing value for {}", args.last().unwrap()));
+    }
+    if options.labels.is_empty() || !options.labels.chars().all_unique() {
+        return Err(format!("deck labels {:?} must be distinct and not empty", options.labels));
+    }
+    if options.labels.chars().count() > MAX_LABELS {
+        return Err(format!("at most {} different cards are supported", MAX_LABELS));
+    }
+    return Ok(options);
+}
+
+pub fn parse_with(input: &str, rules: &dyn Rules) -> REPR {
     input.lines()
         .map(|l| l.split_once(" ").unwrap())
-        .map(|(hand, bid)| (Hand::new(hand, false), bid.parse().unwrap()))
+        .map(|(hand, bid)| (Hand::new(hand, rules), bid.parse().unwrap()))
         .collect()
 }
 
+pub fn parse1(input: &str) -> REPR {
+    parse_with(input, &CamelCards)
+}
+
 pub fn parse2(input: &str) -> REPR {
-    input.lines()
-        .map(|l| l.split_once(" ").unwrap())
-        .map(|(hand, bid)| (Hand::new(hand, true), bid.parse().unwrap()))
-        .collect()
+    parse_with(input, &Jokers)
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--simulate") {
+        let options = parse_simulation(&args[1..]).unwrap_or_else(|e| fail(&e));
+        let deck = options.deck();
+        let (players, hand_size, rounds, seed) = (options.players, options.hand_size, options.rounds, options.seed);
+        println!("{} players, {} cards each, {} rounds, seed {}\n", players, hand_size, rounds, seed);
+        let report = simulate(&deck, &options.rules(), players, hand_size, rounds, seed).unwrap_or_else(|e| fail(&e));
+        println!("without jokers\n{}", report);
+        if let Some(jokers) = options.joker_rules() {
+            let report = simulate(&deck, &jokers, players, hand_size, rounds, seed).unwrap_or_else(|e| fail(&e));
+            println!("with jokers\n{}", report);
+        }
+        return;
+    }
+
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
+    if args.first().map(String::as_str) == Some("--explain") {
+        print!("{}\n{}", explain(parse1(&input), &CamelCards), explain(parse2(&input), &Jokers));
+        return;
+    }
 
     let result1 = compute(parse1(&input));
     println!("{}", result1);    
@@ -155,6 +552,7 @@ fn main() {
 mod tests {
     use super::*;
     use indoc::indoc;
+    use std::time::Instant;
 
     const INPUT: &str = indoc! {"
         32T3K 765
@@ -166,21 +564,229 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(Hand::new("32T3K", false).hand_type, HandType::OnePair);
-        assert_eq!(Hand::new("KK677", false).hand_type, HandType::TwoPair);
-        assert_eq!(Hand::new("KTJJT", false).hand_type, HandType::TwoPair);
-        assert_eq!(Hand::new("T55J5", false).hand_type, HandType::ThreeOfAKind);
-        assert_eq!(Hand::new("QQQJA", false).hand_type, HandType::ThreeOfAKind);
+        assert_eq!(Hand::new("32T3K", &CamelCards).hand_type, HandType::OnePair);
+        assert_eq!(Hand::new("KK677", &CamelCards).hand_type, HandType::TwoPair);
+        assert_eq!(Hand::new("KTJJT", &CamelCards).hand_type, HandType::TwoPair);
+        assert_eq!(Hand::new("T55J5", &CamelCards).hand_type, HandType::ThreeOfAKind);
+        assert_eq!(Hand::new("QQQJA", &CamelCards).hand_type, HandType::ThreeOfAKind);
 
-        let mut to_sort = [Hand::new("QQQJA", false), Hand::new("T55J5", false)];
+        let mut to_sort = [Hand::new("QQQJA", &CamelCards), Hand::new("T55J5", &CamelCards)];
         to_sort.sort();
-        assert_eq!(to_sort, [Hand::new("T55J5", false), Hand::new("QQQJA", false)]);
+        assert_eq!(to_sort, [Hand::new("T55J5", &CamelCards), Hand::new("QQQJA", &CamelCards)]);
 
         assert_eq!(compute(parse1(INPUT)), 6440);
     }
 
     #[test]
     fn test_part2() {
+        assert_eq!(Hand::new("KTJJT", &Jokers).hand_type, HandType::FourOfAKind);
+        assert!(Hand::new("JKKK2", &Jokers) < Hand::new("QQQQ2", &Jokers));
+
         assert_eq!(compute(parse2(INPUT)), 5905);
     }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = CustomRules { card_order: "J2Q3456789TKA".to_owned(), wildcards: "JQ".to_owned() };
+        assert_eq!(Hand::new("JQ2A3", &rules).hand_type, HandType::ThreeOfAKind);
+        assert_eq!(Hand::new("JQJQJ", &rules).hand_type, HandType::FiveOfAKind);
+        assert!(Hand::new("2QKKK", &rules) < Hand::new("3QKKK", &rules));
+
+        assert_eq!(Hand::new("A2345", &SuitlessPoker).hand_type, HandType::Straight);
+        assert_eq!(Hand::new("9TJQK", &SuitlessPoker).hand_type, HandType::Straight);
+        assert_eq!(Hand::new("9TJQA", &SuitlessPoker).hand_type, HandType::HighCard);
+        assert_eq!(Hand::new("QQQJA", &SuitlessPoker).hand_type, HandType::ThreeOfAKind);
+        assert!(Hand::new("QQQJA", &SuitlessPoker) < Hand::new("23456", &SuitlessPoker));
+        assert!(Hand::new("23456", &SuitlessPoker) < Hand::new("22333", &SuitlessPoker));
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(explain(parse1(INPUT), &CamelCards), indoc! {"
+            rank 1: (32T3K, OnePair) bid 765 wins 765
+            rank 2: (KTJJT, TwoPair) bid 220 wins 440, beats rank 1 on hand type TwoPair > OnePair
+            rank 3: (KK677, TwoPair) bid 28 wins 84, beats rank 2 on card 2: K > T
+            rank 4: (T55J5, ThreeOfAKind) bid 684 wins 2736, beats rank 3 on hand type ThreeOfAKind > TwoPair
+            rank 5: (QQQJA, ThreeOfAKind) bid 483 wins 2415, beats rank 4 on card 1: Q > T
+        "});
+        assert_eq!(explain(parse2(INPUT), &Jokers), indoc! {"
+            rank 1: (32T3K, OnePair) bid 765 wins 765
+            rank 2: (KK677, TwoPair) bid 28 wins 56, beats rank 1 on hand type TwoPair > OnePair
+            rank 3: (T55J5, FourOfAKind) as T5555 bid 684 wins 2052, beats rank 2 on hand type FourOfAKind > TwoPair
+            rank 4: (QQQJA, FourOfAKind) as QQQQA bid 483 wins 1932, beats rank 3 on card 1: Q > T
+            rank 5: (KTJJT, FourOfAKind) as KTTTT bid 220 wins 1100, beats rank 4 on card 1: K > Q
+        "});
+        assert_eq!(Hand::new("JJJJJ", &Jokers).substitution(&Jokers), Some("AAAAA".to_owned()));
+        assert_eq!(Hand::new("JK2K2", &Jokers).substitution(&Jokers), Some("KK2K2".to_owned()));
+
+        let decider = |stronger: &str, weaker: &str, rules: &dyn Rules| Hand::new(stronger, rules).decider(&Hand::new(weaker, rules));
+        assert_eq!(decider("KK677", "KTJJT", &CamelCards), "card 2: K > T");
+        assert_eq!(decider("AsKs2h3d5c", "AhKs2h3d5c", &Poker), "tie");
+        assert_eq!(decider("AsKs2h3d5c", "AhQs2h3d5c", &Poker), "card 2: K > Q");
+        assert_eq!(decider("3456K2", "3456K", &CamelCards), "length 6 > 5");
+    }
+
+    #[test]
+    fn test_hand_sizes() {
+        let hand_type = |cards: &str| Hand::new(cards, &CamelCards).hand_type;
+        assert_eq!(hand_type("A"), HandType::HighCard);
+        assert_eq!(hand_type("AA"), HandType::OnePair);
+        assert_eq!(hand_type("AAA"), HandType::ThreeOfAKind);
+        assert_eq!(hand_type("AAAK"), HandType::ThreeOfAKind);
+        assert_eq!(hand_type("AAKK"), HandType::TwoPair);
+        assert_eq!(hand_type("2345678"), HandType::HighCard);
+        assert_eq!(hand_type("22334456"), HandType::TwoPair);
+        assert_eq!(hand_type("2223344"), HandType::FullHouse);
+        assert_eq!(hand_type("2222333"), HandType::FourOfAKind);
+        assert_eq!(hand_type("2222222"), HandType::FiveOfAKind);
+        assert_eq!(Hand::new("JJJJJJ", &Jokers).hand_type, HandType::FiveOfAKind);
+        assert_eq!(Hand::new("2J", &Jokers).hand_type, HandType::OnePair);
+    }
+
+    #[test]
+    fn test_poker_categories() {
+        let hand_type = |cards: &str| Hand::new(cards, &Poker).hand_type;
+        assert_eq!(hand_type("2h5c9dJsKs"), HandType::HighCard);
+        assert_eq!(hand_type("2h2c9dJsKs"), HandType::OnePair);
+        assert_eq!(hand_type("2h2c9d9sKs"), HandType::TwoPair);
+        assert_eq!(hand_type("2h2c2d9sKs"), HandType::ThreeOfAKind);
+        assert_eq!(hand_type("Th9cJdQsKs"), HandType::Straight);
+        assert_eq!(hand_type("Ah2c3d4s5s"), HandType::Straight);
+        assert_eq!(hand_type("2s5s9sJsKs"), HandType::Flush);
+        assert_eq!(hand_type("2h2c2d9s9h"), HandType::FullHouse);
+        assert_eq!(hand_type("2h2c2d2sKs"), HandType::FourOfAKind);
+        assert_eq!(hand_type("ThJhQhKhAh"), HandType::StraightFlush);
+        assert_eq!(hand_type("QhKhAh"), HandType::StraightFlush);
+        assert_eq!(hand_type("QhKhAh2h3h"), HandType::Flush);
+        assert_eq!(hand_type("2h3h"), HandType::HighCard);
+        assert_eq!(hand_type("2h3h4h"), HandType::StraightFlush);
+
+        // longer hands only need five of their cards
+        assert_eq!(hand_type("2h3c4d5s6sKsKh"), HandType::Straight);
+        assert_eq!(hand_type("2h3c4d5s7sKsKh"), HandType::OnePair);
+        assert_eq!(hand_type("2s3c9s5s7sKsKh"), HandType::Flush);
+        assert_eq!(hand_type("9h2h3h4h5hAhKc"), HandType::StraightFlush);
+        assert_eq!(hand_type("2h3h4h5h6cKhQc"), HandType::Flush);
+        assert_eq!(hand_type("2h3h4h5h6cKhKcKs2c"), HandType::FullHouse);
+        assert_eq!(Hand::new("23456KK", &SuitlessPoker).hand_type, HandType::Straight);
+        assert_eq!(Hand::new("A2345KK", &SuitlessPoker).hand_type, HandType::Straight);
+        assert_eq!(Hand::new("2h2c2d2s2h", &Poker).hand_type, HandType::FiveOfAKind);
+
+        assert!(Hand::new("2s5s9sJsKs", &Poker) > Hand::new("Th9cJdQsKs", &Poker));
+        assert_eq!(Hand::new("ThJhQhKhAh", &Poker).to_string(), "(ThJhQhKhAh, StraightFlush)");
+    }
+
+    #[test]
+    fn test_simulate() {
+        let deck = Deck::new("23456789TJQKA", 4);
+        let report = simulate(&deck, &CamelCards, 4, 5, 2000, 7).unwrap();
+        assert_eq!(Ok(&report), simulate(&deck, &CamelCards, 4, 5, 2000, 7).as_ref());
+        assert_ne!(Ok(&report), simulate(&deck, &CamelCards, 4, 5, 2000, 8).as_ref());
+        assert_eq!(report.hands, 8000);
+        assert_eq!(report.per_type.values().map(|s| s.dealt).sum::<u64>(), 8000);
+        assert!(report.per_type.values().map(|s| s.wins).sum::<u64>() >= 2000);
+        // one deck only has four copies of each card
+        assert!(!report.per_type.contains_key(&HandType::FiveOfAKind));
+        assert!(report.per_type[&HandType::HighCard].dealt > report.per_type[&HandType::ThreeOfAKind].dealt);
+
+        // the same seed deals the same cards, and jokers never make a hand worse
+        let mut rng = Rng::new(3);
+        for cards in deck.deal(&mut rng, 10, 5).unwrap() {
+            assert!(Hand::new(&cards, &Jokers).hand_type >= Hand::new(&cards, &CamelCards).hand_type);
+        }
+        let with_jokers = simulate(&deck, &Jokers, 4, 5, 2000, 7).unwrap();
+        assert!(with_jokers.per_type[&HandType::HighCard].dealt < report.per_type[&HandType::HighCard].dealt);
+        assert!(report.to_string().starts_with("type"));
+
+        assert!(simulate(&deck, &CamelCards, 0, 5, 10, 7).is_err());
+        assert!(simulate(&deck, &CamelCards, 4, 5, 0, 7).is_err());
+        assert_eq!(simulate(&deck, &CamelCards, 11, 5, 10, 7), Err("52 cards are not enough to deal 11 hands of 5".to_owned()));
+        assert!(deck.deal(&mut rng, usize::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn test_simulation_options() {
+        let options = parse_simulation(&["--labels", "AKQJ", "--copies", "3", "--hand-size", "2", "--players", "6"].map(String::from)).unwrap();
+        assert_eq!(options.deck().cards.len(), 12);
+        assert_eq!(options.joker_rules().map(|r| r.card_order), Some("JAKQ".to_owned()));
+        let report = simulate(&options.deck(), &options.rules(), options.players, options.hand_size, 50, options.seed).unwrap();
+        assert_eq!(report.hands, 300);
+        assert!(simulate(&options.deck(), &options.rules(), 7, options.hand_size, 50, options.seed).is_err());
+
+        let default = parse_simulation(&[]).unwrap();
+        assert_eq!(default.joker_rules().map(|r| r.card_order), Some(Jokers.card_order().to_owned()));
+        assert!(parse_simulation(&["--labels", "AKQ"].map(String::from)).unwrap().joker_rules().is_none());
+        assert!(parse_simulation(&["--labels", "AKA"].map(String::from)).is_err());
+        assert!(parse_simulation(&["--players", "x"].map(String::from)).is_err());
+        assert!(parse_simulation(&["--players"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_rng_zero_state() {
+        let mut rng = Rng::new(0x9E3779B97F4A7C15);
+        let values: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert!(values.iter().all(|v| *v != 0));
+        assert_ne!(values[0], values[1]);
+        // consecutive rounds deal different cards
+        let deck = Deck::new("23456789TJQKA", 4);
+        assert_ne!(deck.deal(&mut rng, 4, 5), deck.deal(&mut rng, 4, 5));
+    }
+
+    fn random_hands(count: usize, hand_size: usize, rules: &dyn Rules) -> Vec<Hand> {
+        let mut rng = Rng::new(0);
+        let labels: Vec<char> = rules.card_order().chars().collect();
+        (0..count)
+            .map(|_| {
+                let cards: String = (0..hand_size)
+                    .map(|_| labels[rng.below(labels.len())])
+                    .collect();
+                Hand::new(&cards, rules)
+            })
+            .collect()
+    }
+
+    fn structural_cmp(a: &Hand, b: &Hand) -> Ordering {
+        a.hand_type.cmp(&b.hand_type).then(a.cards.cmp(&b.cards))
+    }
+
+    #[test]
+    fn test_sort_key() {
+        for (hand_size, rules) in [(5, &CamelCards as &dyn Rules), (5, &Jokers), (3, &Jokers), (25, &CamelCards)] {
+            let hands = random_hands(300, hand_size, rules);
+            for a in &hands {
+                for b in &hands {
+                    assert_eq!(a.cmp(b), structural_cmp(a, b), "{} {}", a, b);
+                }
+            }
+        }
+        assert!(Hand::new("AA", &CamelCards) < Hand::new("AA2", &CamelCards));
+        assert!(Hand::new("23", &CamelCards) < Hand::new("32", &CamelCards));
+    }
+
+    // run with `cargo test --release --bin day7 -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_sort() {
+        let start = Instant::now();
+        let mut hands = random_hands(3_000_000, 5, &Jokers);
+        let parse_time = start.elapsed();
+        let mut structural = random_hands(3_000_000, 5, &Jokers);
+
+        let start = Instant::now();
+        structural.sort_by(structural_cmp);
+        let structural_time = start.elapsed();
+
+        let start = Instant::now();
+        hands.sort_unstable_by_key(|h| h.key);
+        let key_time = start.elapsed();
+
+        assert!(hands.iter().zip(&structural).all(|(a, b)| a.key == b.key));
+        println!("{} hands: built in {:?}, sorted by cards {:?}, by key {:?}",
+            hands.len(), parse_time, structural_time, key_time);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
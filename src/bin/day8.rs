@@ -1,82 +1,424 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::io;
 use std::io::Read;
-use std::fmt::Display;
+use std::process;
 
 use regex::Regex;
 
-use num::integer::lcm;
+use itertools::Itertools;
+use num::integer::{ExtendedGcd, Integer};
 
 type REPR = (Instructions, Network);
 
-pub struct Instructions(Vec<char>);
+// 0 for left, 1 for right, to index the adjacency directly
+pub struct Instructions(Vec<usize>);
 
+pub type NodeId = u32;
 
+// node names are interned into dense ids at parse time
+#[derive(Default)]
 pub struct Network {
-    nodes: HashMap<Element, (Element, Element)>
+    names: Vec<String>,
+    ids: HashMap<String, NodeId>,
+    edges: Vec<[NodeId; 2]>,
 }
 
-#[derive(PartialEq, Eq, Hash)]
-pub struct Element(String);
+impl Network {
+    fn intern(&mut self, name: &str) -> NodeId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len() as NodeId;
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        self.edges.push([NodeId::MAX; 2]);
+        return id;
+    }
 
+    pub fn id(&self, name: &str) -> Option<NodeId> {
+        return self.ids.get(name).copied();
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        return &self.names[id as usize];
+    }
+
+    pub fn nodes_matching(&self, predicate: &dyn Fn(&str) -> bool) -> Vec<NodeId> {
+        return (0..self.names.len() as NodeId).filter(|id| predicate(self.name(*id))).collect();
+    }
+
+    fn marks(&self, predicate: &dyn Fn(&str) -> bool) -> Vec<bool> {
+        return self.names.iter().map(|n| predicate(n)).collect();
+    }
 
-pub fn steps_from_to(input: &REPR, from: &Element, goal_reached: fn(&Element) -> bool) -> u64 {
+    fn next(&self, id: NodeId, direction: usize) -> NodeId {
+        return self.edges[id as usize][direction];
+    }
+
+    // nodes that no path from any of the starts leads to
+    pub fn unreachable_from(&self, starts: &[NodeId]) -> Vec<NodeId> {
+        let mut reached = vec![false; self.names.len()];
+        let mut todo = starts.to_vec();
+        while let Some(id) = todo.pop() {
+            if !reached[id as usize] {
+                reached[id as usize] = true;
+                todo.extend(self.edges[id as usize]);
+            }
+        }
+        return (0..self.names.len() as NodeId).filter(|id| !reached[*id as usize]).collect();
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WalkMode {
+    // checks for the goal after every single step
+    Exact,
+    // only checks for the goal after following all instructions, which saves
+    // the checks but overcounts when the goal is reached halfway through
+    WholeBlocks,
+}
+
+pub fn steps_from_to(input: &REPR, from: NodeId, goal_reached: &dyn Fn(&str) -> bool, mode: WalkMode) -> Result<u64, String> {
+    let (instructions, network) = input;
+    let is_goal = network.marks(goal_reached);
     let mut steps = 0;
-    let mut current_element = from;
-    let instructions = &input.0.0;
-
-    while !goal_reached(current_element) {
-        steps += &instructions.len();
-        for instr in instructions {
-            let (l, r) = input.1.nodes.get(&current_element).unwrap();
-            match instr {
-                'L' => current_element = l,
-                'R' => current_element = r,
-                _ => panic!("unknown instruction {}", instr),
-            }    
+    let mut current = from;
+    // a (node, instruction) state that repeats without a goal repeats forever,
+    // checking the states at instruction 0 is enough to notice
+    let mut seen_at_block_start: HashSet<NodeId> = HashSet::new();
+
+    while !is_goal[current as usize] {
+        if !seen_at_block_start.insert(current) {
+            return Err(format!("no goal can be reached from {}", network.name(from)));
+        }
+        for &direction in &instructions.0 {
+            current = network.next(current, direction);
+            steps += 1;
+            if mode == WalkMode::Exact && is_goal[current as usize] {
+                break;
+            }
+        }
+    }
+
+    return Ok(steps);
+}
+
+// the walk of one ghost, which must eventually repeat a (node, instruction) state
+#[derive(PartialEq, Eq, Debug)]
+pub struct GhostCycle {
+    // the step at which the repeating part starts
+    start: u64,
+    length: u64,
+    // goal steps before the repeating part
+    goals_before_cycle: Vec<u64>,
+    // goal steps within the first round of the repeating part,
+    // they reoccur every `length` steps
+    goals_in_cycle: Vec<u64>,
+}
+
+impl GhostCycle {
+    fn is_goal_step(&self, step: u64) -> bool {
+        if step < self.start {
+            return self.goals_before_cycle.contains(&step);
         }
+        let in_first_round = self.start + (step - self.start) % self.length;
+        return self.goals_in_cycle.contains(&in_first_round);
     }
+}
+
+pub fn analyse_cycle(input: &REPR, from: NodeId, goal_reached: &dyn Fn(&str) -> bool) -> GhostCycle {
+    let (instructions, network) = input;
+    let instructions = &instructions.0;
+    let is_goal = network.marks(goal_reached);
+    // first step at which each (node, instruction) state was seen
+    let mut seen: HashMap<(NodeId, usize), u64> = HashMap::new();
+    let mut goals = Vec::new();
+    let mut current = from;
 
-    return steps as u64;
+    for step in 0.. {
+        let instr_idx = step as usize % instructions.len();
+        if let Some(&start) = seen.get(&(current, instr_idx)) {
+            let (goals_before_cycle, goals_in_cycle) = goals.into_iter().partition(|g| *g < start);
+            return GhostCycle { start, length: step - start, goals_before_cycle, goals_in_cycle };
+        }
+        seen.insert((current, instr_idx), step);
+        if is_goal[current as usize] {
+            goals.push(step);
+        }
+        current = network.next(current, instructions[instr_idx]);
+    }
+    unreachable!();
 }
 
-pub fn compute_1(input: REPR) -> u64 {
-    return steps_from_to(&input, &Element("AAA".to_owned()), |e| *e == Element("ZZZ".to_owned()));
+// solves x = a1 mod m1 and x = a2 mod m2, the moduli don't need to be coprime,
+// Ok(None) if there is no solution
+fn crt(a1: i128, m1: i128, a2: i128, m2: i128) -> Result<Option<(i128, i128)>, String> {
+    let overflow = || "common step does not fit in i128".to_owned();
+    let ExtendedGcd { gcd, x, .. } = m1.extended_gcd(&m2);
+    if (a2 - a1) % gcd != 0 {
+        return Ok(None);
+    }
+    let lcm = m1.checked_mul(m2 / gcd).ok_or_else(overflow)?;
+    let k = ((a2 - a1) / gcd).rem_euclid(m2 / gcd)
+        .checked_mul(x).ok_or_else(overflow)?
+        .rem_euclid(m2 / gcd);
+    let x = m1.checked_mul(k).and_then(|n| n.checked_add(a1)).ok_or_else(overflow)?;
+    return Ok(Some((x.rem_euclid(lcm), lcm)));
 }
 
-pub fn compute_2(input: REPR) -> u64 {
-    let starting_elements: Vec<&Element> = input.1.nodes.keys().filter(|e| e.0.ends_with("A")).collect();
-    let steps_needed: Vec<u64> = starting_elements.into_iter()
-        .map(|start| steps_from_to(&input, start, |e| e.0.ends_with("Z")))
+// first step at which every ghost is at a goal at the same time
+pub fn first_common_goal(cycles: &[GhostCycle]) -> Result<u64, String> {
+    // before every ghost is in its cycle, a common step must be a goal before some cycle
+    let latest_start = cycles.iter().map(|c| c.start).max().unwrap_or(0);
+    let early = cycles.iter()
+        .flat_map(|c| c.goals_before_cycle.iter().copied())
+        .sorted()
+        .find(|step| cycles.iter().all(|c| c.is_goal_step(*step)));
+    if let Some(step) = early {
+        return Ok(step);
+    }
+
+    // afterwards, every ghost has to pick one of its goal offsets
+    let mut best: Option<u64> = None;
+    for offsets in cycles.iter().map(|c| c.goals_in_cycle.iter().map(move |g| (*g as i128, c.length as i128))).multi_cartesian_product() {
+        let mut solution = Some((0, 1));
+        for (goal, length) in offsets {
+            let Some((a, m)) = solution else { break };
+            solution = crt(a, m, goal, length)?;
+        }
+        if let Some((residue, modulus)) = solution {
+            let latest_start = latest_start as i128;
+            let step = if residue >= latest_start {
+                residue
+            } else {
+                Integer::div_ceil(&(latest_start - residue), &modulus).checked_mul(modulus)
+                    .and_then(|n| n.checked_add(residue))
+                    .ok_or("common step does not fit in i128")?
+            };
+            let step = u64::try_from(step).map_err(|_| format!("common step {} does not fit in u64", step))?;
+            best = Some(best.map_or(step, |b| b.min(step)));
+        }
+    }
+    return best.ok_or("the ghosts never reach their goals at the same step".to_owned());
+}
+
+// nodes visited in the first `steps` steps, including the start
+fn walk(input: &REPR, from: NodeId, steps: u64) -> Vec<NodeId> {
+    let (instructions, network) = input;
+    let mut result = vec![from];
+    let mut current = from;
+    for step in 0..steps {
+        current = network.next(current, instructions.0[step as usize % instructions.0.len()]);
+        result.push(current);
+    }
+    return result;
+}
+
+const CYCLE_COLORS: [&str; 6] = ["blue", "red", "darkgreen", "orange", "purple", "brown"];
+
+pub fn to_dot(input: &REPR, is_start: &dyn Fn(&str) -> bool, is_goal: &dyn Fn(&str) -> bool) -> String {
+    let (instructions, network) = input;
+
+    // (node, direction) of every edge on a ghost's cycle, and the color of that ghost
+    let mut cycle_edges: HashMap<(NodeId, usize), &str> = HashMap::new();
+    for (ghost, start) in network.nodes_matching(is_start).into_iter().enumerate() {
+        let color = CYCLE_COLORS[ghost % CYCLE_COLORS.len()];
+        let cycle = analyse_cycle(input, start, is_goal);
+        let path = walk(input, start, cycle.start + cycle.length);
+        for step in cycle.start..cycle.start + cycle.length {
+            let direction = instructions.0[step as usize % instructions.0.len()];
+            cycle_edges.entry((path[step as usize], direction)).or_insert(color);
+        }
+    }
+
+    let mut result = String::from("digraph network {\n    node [style=filled, fillcolor=white];\n");
+    for (id, name) in network.names.iter().enumerate() {
+        if is_start(name) {
+            result += &format!("    \"{}\" [fillcolor=palegreen];\n", name);
+        } else if is_goal(name) {
+            result += &format!("    \"{}\" [fillcolor=lightcoral];\n", name);
+        }
+        for (direction, label) in ["L", "R"].iter().enumerate() {
+            let target = network.name(network.edges[id][direction]);
+            let style = match cycle_edges.get(&(id as NodeId, direction)) {
+                Some(color) => format!(", color={}, penwidth=2", color),
+                None => String::new(),
+            };
+            result += &format!("    \"{}\" -> \"{}\" [label=\"{}\"{}];\n", name, target, label, style);
+        }
+    }
+    result += "}\n";
+    return result;
+}
+
+// first step at which a walk from every start is at a goal at the same time
+pub fn route(input: &REPR, is_start: &dyn Fn(&str) -> bool, is_goal: &dyn Fn(&str) -> bool) -> Result<u64, String> {
+    let starts = input.1.nodes_matching(is_start);
+    if starts.is_empty() {
+        return Err("no node matches the start".to_owned());
+    }
+    let cycles: Vec<GhostCycle> = starts.into_iter()
+        .map(|start| analyse_cycle(input, start, is_goal))
         .collect();
+    return first_common_goal(&cycles);
+}
+
+// matches whole node names
+pub struct NamePattern(Regex);
+
+impl NamePattern {
+    // `*` matches any run of characters and `?` a single one
+    pub fn glob(glob: &str) -> NamePattern {
+        let re: String = glob.chars()
+            .map(|c| match c {
+                '*' => ".*".to_owned(),
+                '?' => ".".to_owned(),
+                _ => regex::escape(&c.to_string()),
+            })
+            .collect();
+        return NamePattern(Regex::new(&format!("^{}$", re)).unwrap());
+    }
+
+    pub fn regex(re: &str) -> Result<NamePattern, String> {
+        return Regex::new(&format!("^(?:{})$", re))
+            .map(NamePattern)
+            .map_err(|e| format!("invalid regex {}: {}", re, e));
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        return self.0.is_match(name);
+    }
+}
 
-    let lcm = steps_needed.into_iter().reduce(|a, b| lcm(a, b)).unwrap();
-    return lcm as u64;
+pub struct RouteOptions {
+    start: NamePattern,
+    goal: NamePattern,
 }
 
-pub fn parse(input: &str) -> REPR {
-    let node_re: Regex = Regex::new(r"^([A-Z1-9]+) = \(([A-Z1-9]+), ([A-Z1-9]+)\)$").unwrap();
-    let lines: Vec<&str> = input.lines().collect();
+impl Default for RouteOptions {
+    fn default() -> Self {
+        RouteOptions { start: NamePattern::glob("*A"), goal: NamePattern::glob("*Z") }
+    }
+}
 
-    let instructions: Vec<char> = lines[0].chars().collect();
+pub fn parse_options(args: &[String]) -> Result<RouteOptions, String> {
+    let mut options = RouteOptions::default();
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {}", option))?;
+        match option.as_str() {
+            "--start" => options.start = NamePattern::glob(value),
+            "--goal" => options.goal = NamePattern::glob(value),
+            "--start-regex" => options.start = NamePattern::regex(value)?,
+            "--goal-regex" => options.goal = NamePattern::regex(value)?,
+            _ => return Err(format!("unknown option {}", option)),
+        }
+    }
+    return Ok(options);
+}
 
-    let nodes = lines[2..].iter()
-        .map(|&l| {
-            let caps = node_re.captures(l).unwrap();
-            let name = caps[1].parse().unwrap();
-            let left = caps[2].parse().unwrap();
-            let right = caps[3].parse().unwrap();
-            (Element(name), Element(left), Element(right))})
-        .fold(HashMap::new(), |mut acc, e| {
-            acc.insert(e.0, (e.1, e.2));
-            acc
-        });
+pub fn compute_1(input: REPR) -> Result<u64, String> {
+    let start = input.1.id("AAA").ok_or("no node named AAA")?;
+    input.1.id("ZZZ").ok_or("no node named ZZZ")?;
+    return steps_from_to(&input, start, &|n| n == "ZZZ", WalkMode::Exact);
+}
 
-    return (Instructions(instructions), Network {nodes});
+pub fn compute_2(input: REPR) -> Result<u64, String> {
+    return route(&input, &|n| n.ends_with('A'), &|n| n.ends_with('Z'));
+}
+
+pub fn try_parse(input: &str) -> Result<REPR, String> {
+    let node_re: Regex = Regex::new(r"^(\w+)\s*=\s*\(\s*(\w+)\s*,\s*(\w+)\s*\)$").unwrap();
+    let mut lines = input.lines()
+        .enumerate()
+        .map(|(idx, l)| (idx + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty());
+
+    let (first_line_no, first_line) = lines.next().ok_or("no instructions".to_owned())?;
+    let instructions: Vec<usize> = first_line.chars()
+        .map(|c| match c {
+            'L' => Ok(0),
+            'R' => Ok(1),
+            _ => Err(format!("line {}: unknown instruction {}", first_line_no, c)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut network = Network::default();
+    // line of the first reference to every node
+    let mut referenced_at: HashMap<NodeId, usize> = HashMap::new();
+    for (line, l) in lines {
+        let caps = node_re.captures(l).ok_or(format!("line {}: cannot parse {:?}", line, l))?;
+        let id = network.intern(&caps[1]);
+        if network.edges[id as usize][0] != NodeId::MAX {
+            return Err(format!("line {}: node {} is defined twice", line, &caps[1]));
+        }
+        let left = network.intern(&caps[2]);
+        let right = network.intern(&caps[3]);
+        referenced_at.entry(left).or_insert(line);
+        referenced_at.entry(right).or_insert(line);
+        network.edges[id as usize] = [left, right];
+    }
+
+    let dangling: Vec<String> = (0..network.names.len() as NodeId)
+        .filter(|id| network.edges[*id as usize][0] == NodeId::MAX)
+        .map(|id| format!("line {}: node {} is referenced but never defined", referenced_at[&id], network.name(id)))
+        .collect();
+    if !dangling.is_empty() {
+        return Err(dangling.join("\n"));
+    }
+
+    return Ok((Instructions(instructions), network));
+}
+
+pub fn parse(input: &str) -> REPR {
+    return try_parse(input).unwrap_or_else(|e| panic!("{}", e));
 }
 
 fn main() {
-    read_and_write(parse, &[compute_1, compute_2]);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = || match parse_options(&args[1..]) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        },
+    };
+    match args.first().map(String::as_str) {
+        Some("--check") => {
+            let options = options();
+            match try_parse(&read_input()) {
+                Ok((_, network)) => {
+                    let starts = network.nodes_matching(&|n| options.start.matches(n));
+                    for id in network.unreachable_from(&starts) {
+                        println!("node {} cannot be reached from any start", network.name(id));
+                    }
+                },
+                Err(e) => println!("{}", e),
+            }
+        },
+        Some("--dot") => {
+            let options = options();
+            print!("{}", to_dot(&parse(&read_input()), &|n| options.start.matches(n), &|n| options.goal.matches(n)));
+        },
+        Some("--route") => {
+            let options = options();
+            match route(&parse(&read_input()), &|n| options.start.matches(n), &|n| options.goal.matches(n)) {
+                Ok(steps) => println!("{}", steps),
+                Err(e) => eprintln!("{}", e),
+            }
+        },
+        _ => {
+            let input = read_input();
+            for compute in [compute_1, compute_2] {
+                match compute(parse(&input)) {
+                    Ok(result) => println!("{}", result),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        },
+    }
 }
 
 #[cfg(test)]
@@ -94,7 +436,47 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(compute_1(parse(INPUT1)), 6);
+        assert_eq!(compute_1(parse(INPUT1)), Ok(6));
+        assert_eq!(compute_1(parse(INPUT2)), Err("no node named AAA".to_owned()));
+    }
+
+    #[test]
+    fn test_walk_modes() {
+        let input = parse(indoc! {"
+            LLR
+
+            AAA = (BBB, BBB)
+            BBB = (ZZZ, AAA)
+            ZZZ = (ZZZ, ZZZ)
+        "});
+        let start = input.1.id("AAA").unwrap();
+        let goal = &|n: &str| n == "ZZZ";
+        assert_eq!(steps_from_to(&input, start, goal, WalkMode::Exact), Ok(2));
+        assert_eq!(steps_from_to(&input, start, goal, WalkMode::WholeBlocks), Ok(3));
+        assert_eq!(steps_from_to(&input, input.1.id("ZZZ").unwrap(), goal, WalkMode::Exact), Ok(0));
+
+        let input = parse(INPUT1);
+        let start = input.1.id("AAA").unwrap();
+        assert_eq!(steps_from_to(&input, start, goal, WalkMode::Exact), Ok(6));
+        assert_eq!(steps_from_to(&input, start, goal, WalkMode::WholeBlocks), Ok(6));
+
+        let stuck = parse(indoc! {"
+            LR
+
+            AAA = (BBB, ZZZ)
+            BBB = (BBB, AAA)
+            ZZZ = (ZZZ, ZZZ)
+        "});
+        let start = stuck.1.id("AAA").unwrap();
+        let error = Err("no goal can be reached from AAA".to_owned());
+        assert_eq!(steps_from_to(&stuck, start, goal, WalkMode::Exact), error);
+        assert_eq!(steps_from_to(&stuck, start, goal, WalkMode::WholeBlocks), error);
+        assert_eq!(compute_1(parse(indoc! {"
+            L
+
+            AAA = (AAA, AAA)
+            ZZZ = (ZZZ, ZZZ)
+        "})), error);
     }
 
     const INPUT2: &str = indoc! {"
@@ -112,20 +494,172 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        assert_eq!(compute_2(parse(INPUT2)), 6);
+        assert_eq!(compute_2(parse(INPUT2)), Ok(6));
+    }
+
+    #[test]
+    fn test_validation() {
+        let (_, network) = parse(indoc! {"
+            RL
+
+            start_0 = (a_1, zz)
+            a_1 = ( zz , start_0 )
+            zz = (zz, zz)
+            orphan = (a_1, zz)
+        "});
+        assert_eq!(network.names.len(), 4);
+        let start = network.id("start_0").unwrap();
+        let unreachable: Vec<&str> = network.unreachable_from(&[start]).into_iter().map(|id| network.name(id)).collect();
+        assert_eq!(unreachable, vec!["orphan"]);
+
+        assert_eq!(try_parse(indoc! {"
+            L
+
+            AAA = (bbb, ccc)
+            bbb = (AAA, ddd)
+        "}).err(), Some("line 3: node ccc is referenced but never defined\nline 4: node ddd is referenced but never defined".to_owned()));
+        assert_eq!(try_parse("L\n\nAAA = (AAA, AAA)\nAAA = (AAA, AAA)\n").err(), Some("line 4: node AAA is defined twice".to_owned()));
+        assert_eq!(try_parse("L\n\nAAA = AAA\n").err(), Some("line 3: cannot parse \"AAA = AAA\"".to_owned()));
+        assert_eq!(try_parse("LX\n").err(), Some("line 1: unknown instruction X".to_owned()));
+    }
+
+    #[test]
+    fn test_route_options() {
+        let input = parse(INPUT2);
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let options = parse_options(&args(&[])).unwrap();
+        assert_eq!(route(&input, &|n| options.start.matches(n), &|n| options.goal.matches(n)), Ok(6));
+
+        let options = parse_options(&args(&["--start", "1?A", "--goal", "*Z"])).unwrap();
+        assert_eq!(route(&input, &|n| options.start.matches(n), &|n| options.goal.matches(n)), Ok(2));
+
+        let options = parse_options(&args(&["--start-regex", "2+A", "--goal-regex", "22[BZ]"])).unwrap();
+        assert_eq!(route(&input, &|n| options.start.matches(n), &|n| options.goal.matches(n)), Ok(1));
+
+        let options = parse_options(&args(&["--start", "X*", "--goal", "11Z"])).unwrap();
+        assert!(route(&input, &|n| options.start.matches(n), &|n| options.goal.matches(n)).is_err());
+        assert!(route(&input, &|n| n == "nope", &|n| options.goal.matches(n)).is_err());
+
+        let target = "22C".to_owned();
+        assert_eq!(route(&input, &|n| n == "22A", &|n| *n == target), Ok(2));
+
+        assert!(NamePattern::glob("1*").matches("11Z"));
+        assert!(!NamePattern::glob("1*").matches("21Z"));
+        assert!(!NamePattern::glob("1.Z").matches("11Z"));
+        assert!(parse_options(&args(&["--goal-regex", "("])).is_err());
+        assert!(parse_options(&args(&["--start"])).is_err());
+        assert!(parse_options(&args(&["--via", "X"])).is_err());
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = to_dot(&parse(INPUT2), &|n| n.ends_with('A'), &|n| n.ends_with('Z'));
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("    \"11A\" [fillcolor=palegreen];\n"));
+        assert!(dot.contains("    \"22Z\" [fillcolor=lightcoral];\n"));
+        assert!(dot.contains("    \"11A\" -> \"11B\" [label=\"L\"];\n"));
+        // 11A walks 11B, 11Z, 11B, ... in blue
+        assert!(dot.contains("    \"11B\" -> \"11Z\" [label=\"R\", color=blue, penwidth=2];\n"));
+        assert!(dot.contains("    \"11Z\" -> \"11B\" [label=\"L\", color=blue, penwidth=2];\n"));
+        assert!(dot.contains("    \"22C\" -> \"22Z\" [label=\"L\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"XXX\" -> \"XXX\" [label=\"R\"];\n"));
+        assert_eq!(dot.matches(" -> ").count(), 16);
+    }
+
+    fn ghost_cycles(input: &REPR) -> Vec<GhostCycle> {
+        input.1.nodes_matching(&|n| n.ends_with('A')).into_iter()
+            .sorted_by_key(|id| input.1.name(*id))
+            .map(|start| analyse_cycle(input, start, &|n| n.ends_with('Z')))
+            .collect()
+    }
+
+    #[test]
+    fn test_interning() {
+        let (instructions, network) = parse(INPUT2);
+        assert_eq!(instructions.0, vec![0, 1]);
+        assert_eq!(network.names.len(), 8);
+        let id = network.id("22B").unwrap();
+        assert_eq!(network.name(id), "22B");
+        assert_eq!(network.name(network.next(id, 0)), "22C");
+        assert_eq!(network.id("33A"), None);
+    }
+
+    fn brute_force(cycles: &[GhostCycle], limit: u64) -> Option<u64> {
+        (0..limit).find(|step| cycles.iter().all(|c| c.is_goal_step(*step)))
+    }
+
+    #[test]
+    fn test_cycle_analysis() {
+        let cycles = ghost_cycles(&parse(INPUT2));
+        assert_eq!(cycles[0], GhostCycle { start: 1, length: 2, goals_before_cycle: vec![], goals_in_cycle: vec![2] });
+        assert_eq!(cycles[1], GhostCycle { start: 1, length: 6, goals_before_cycle: vec![], goals_in_cycle: vec![3, 6] });
+
+        // goals at 1, 4, 7, ... and at 2, 4, 6, ...: taking the lcm of the first goals would give 2
+        let not_aligned = parse(indoc! {"
+            L
+
+            AAA = (BBZ, BBZ)
+            BBZ = (CCC, CCC)
+            CCC = (DDD, DDD)
+            DDD = (BBZ, BBZ)
+            XXA = (XXB, XXB)
+            XXB = (XXZ, XXZ)
+            XXZ = (XXB, XXB)
+        "});
+        let cycles = ghost_cycles(&not_aligned);
+        assert_eq!(first_common_goal(&cycles), Ok(4));
+        assert_eq!(brute_force(&cycles, 100), Some(4));
+
+        // a goal before the cycle is the only one that matches
+        let before_cycle = parse(indoc! {"
+            LR
+
+            AAA = (BBB, BBB)
+            BBB = (CCZ, CCZ)
+            CCZ = (DDD, DDD)
+            DDD = (DDD, DDD)
+            XXA = (XXB, XXB)
+            XXB = (XXZ, XXZ)
+            XXZ = (XXB, XXB)
+        "});
+        let cycles = ghost_cycles(&before_cycle);
+        assert_eq!(cycles[0].goals_before_cycle, vec![2]);
+        assert!(cycles[0].goals_in_cycle.is_empty());
+        assert_eq!(first_common_goal(&cycles), Ok(2));
+        assert_eq!(brute_force(&cycles, 100), Some(2));
+
+        let never = parse(indoc! {"
+            L
+
+            AAA = (AAZ, AAZ)
+            AAZ = (AAA, AAA)
+            BBA = (BBB, BBB)
+            BBB = (BBZ, BBZ)
+            BBZ = (BBB, BBB)
+        "});
+        let cycles = ghost_cycles(&never);
+        assert_eq!(brute_force(&cycles, 100), None);
+        assert!(first_common_goal(&cycles).is_err());
+        assert_eq!(compute_2(never), Err("the ghosts never reach their goals at the same step".to_owned()));
+
+        // pairwise coprime cycle lengths whose product is beyond i128
+        let huge: Vec<GhostCycle> = [(1 << 50, 1), ((1 << 50) - 1, 2), ((1 << 50) + 1, 3)].into_iter()
+            .map(|(length, goal)| GhostCycle { start: 0, length, goals_before_cycle: vec![], goals_in_cycle: vec![goal] })
+            .collect();
+        assert_eq!(first_common_goal(&huge), Err("common step does not fit in i128".to_owned()));
+        assert_eq!(crt(0, 1 << 100, 1, (1 << 40) + 1), Err("common step does not fit in i128".to_owned()));
+        assert_eq!(crt(0, 4, 1, 6), Ok(None));
+        assert_eq!(crt(2, 4, 4, 6), Ok(Some((10, 12))));
     }
 }
 
-fn read_and_write<T, S: Display>(parse: fn (&str) -> T, compute: &[fn(T) -> S] ) {
+fn read_input() -> String {
     let mut input = String::new();
 
     io::stdin()
         .read_to_string(&mut input)
         .expect("Failed to read input");
 
-
-    for f in compute {
-        let result = f(parse(&input));
-        println!("{}", result);    
-    }
-}
\ No newline at end of file
+    return input;
+}
@@ -1,69 +1,140 @@
 use std::io;
 use std::io::Read;
+use std::fmt;
 use std::fmt::Display;
 
+use itertools::Itertools;
+
+use num::{BigInt, BigRational, One, Zero};
+
 type REPR = Vec<Sequence>;
 
-#[derive(Debug)]
-pub struct Sequence(Vec<i64>);
+#[derive(Debug, Clone)]
+pub struct Sequence(Vec<BigInt>);
 
 impl Sequence {
     pub fn diffs(&self) -> Sequence {
-        Sequence(self.0.windows(2).map(|w| w[1] - w[0]).collect())
+        Sequence(self.0.windows(2).map(|w| &w[1] - &w[0]).collect())
     }
 
     pub fn is_zeroes(&self) -> bool {
-        return self.0.iter().all(|e| *e == 0);
+        return self.0.iter().all(|e| e.is_zero());
     }
-}
-
 
-pub fn compute_1(input: REPR) -> i64 {
-    let mut sum = 0;
-    for s in input {
-        let mut diffs = vec![s];
-        while !diffs.last().unwrap().is_zeroes() {
-            diffs.push(diffs.last().unwrap().diffs());
+    // fails unless the differences reach all zeroes while at least one value is left,
+    // i.e. the sequence is a polynomial of degree below its length minus one
+    pub fn polynomial(&self) -> Result<Polynomial, String> {
+        if self.0.is_empty() {
+            return Err("empty sequence".to_owned());
         }
-
-        let mut next_val = 0;
-        for diff in diffs.into_iter().rev() {
-            next_val += diff.0.last().unwrap();
+        let mut newton = Vec::new();
+        let mut diffs = self.clone();
+        while !diffs.is_zeroes() {
+            if diffs.0.len() == 1 {
+                return Err(format!("differences of {} never reach all zeroes", self));
+            }
+            newton.push(diffs.0[0].clone());
+            diffs = diffs.diffs();
         }
+        return Ok(Polynomial { newton });
+    }
 
-        sum += next_val;
+    // the value `steps` after the last element, or before the first one for negative steps
+    pub fn extrapolate(&self, steps: i64) -> Result<BigInt, String> {
+        let index = if steps >= 0 { self.0.len() as i64 - 1 + steps } else { steps };
+        return Ok(self.polynomial()?.evaluate(index));
     }
-    
-    return sum;
 }
 
-pub fn compute_2(input: REPR) -> i64 {
-    let mut sum = 0;
-    for s in input {
-        let mut diffs = vec![s];
-        while !diffs.last().unwrap().is_zeroes() {
-            diffs.push(diffs.last().unwrap().diffs());
-        }
+impl Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join(" "))
+    }
+}
 
-        let mut next_val = 0;
-        for diff in diffs.into_iter().rev() {
-            next_val = diff.0.first().unwrap() - next_val;
+// the polynomial through the points (0, s[0]), (1, s[1]), ...
+#[derive(Debug)]
+pub struct Polynomial {
+    // Newton forward form: value(x) = sum of newton[k] * binomial(x, k),
+    // newton[k] is the first element of the k-th differences
+    newton: Vec<BigInt>,
+}
+
+impl Polynomial {
+    pub fn degree(&self) -> Option<usize> {
+        return self.newton.len().checked_sub(1);
+    }
+
+    pub fn evaluate(&self, x: i64) -> BigInt {
+        let x = BigInt::from(x);
+        let mut result = BigInt::zero();
+        // binomial(x, k) = x * (x - 1) * ... * (x - k + 1) / k!, exact for any integer x
+        let mut binomial = BigInt::one();
+        for (k, coefficient) in self.newton.iter().enumerate() {
+            result += coefficient * &binomial;
+            binomial = binomial * (&x - k) / (k + 1);
         }
+        return result;
+    }
 
-        sum += next_val;
+    // the coefficients of 1, x, x^2, ...
+    pub fn coefficients(&self) -> Vec<BigRational> {
+        let mut result = vec![BigRational::zero(); self.newton.len()];
+        // x * (x - 1) * ... * (x - k + 1) / k! in powers of x
+        let mut falling: Vec<BigRational> = vec![BigRational::one()];
+        for (k, coefficient) in self.newton.iter().enumerate() {
+            for (power, c) in falling.iter().enumerate() {
+                result[power] += c * BigRational::from(coefficient.clone());
+            }
+            let factor = BigRational::from(BigInt::from(k));
+            let mut next = vec![BigRational::zero(); falling.len() + 1];
+            for (power, c) in falling.iter().enumerate() {
+                next[power + 1] += c;
+                next[power] -= c * &factor;
+            }
+            let divisor = BigRational::from(BigInt::from(k + 1));
+            falling = next.into_iter().map(|c| c / &divisor).collect();
+        }
+        return result;
     }
-    
-    return sum;}
+}
+
+
+pub fn compute_1(input: REPR) -> Result<BigInt, String> {
+    return extrapolate_all(&input, 1);
+}
+
+pub fn compute_2(input: REPR) -> Result<BigInt, String> {
+    return extrapolate_all(&input, -1);
+}
+
+fn extrapolate_all(input: &REPR, steps: i64) -> Result<BigInt, String> {
+    return input.iter()
+        .enumerate()
+        .map(|(i, s)| s.extrapolate(steps).map_err(|e| format!("sequence {}: {}", i + 1, e)))
+        .sum();
+}
 
 pub fn parse(input: &str) -> REPR {
     return input.lines()
-        .map(|l| l.split(" ").map(|n| n.parse().unwrap()).collect())
-        .map(|v| Sequence(v))
+        .map(|l| l.split_whitespace().map(|n| n.parse().unwrap()).collect())
+        .map(Sequence)
         .collect();
 }
 
 fn main() {
-    read_and_write(parse, &[compute_1, compute_2]);
+    let mut input = String::new();
+
+    io::stdin()
+        .read_to_string(&mut input)
+        .expect("Failed to read input");
+
+    for compute in [compute_1, compute_2] {
+        match compute(parse(&input)) {
+            Ok(result) => println!("{}", result),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -79,25 +150,65 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(compute_1(parse(INPUT)), 114);
+        assert_eq!(compute_1(parse(INPUT)), Ok(BigInt::from(114)));
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(compute_2(parse(INPUT)), 2);
+        assert_eq!(compute_2(parse(INPUT)), Ok(BigInt::from(2)));
     }
-}
 
-fn read_and_write<T, S: Display>(parse: fn (&str) -> T, compute: &[fn(T) -> S] ) {
-    let mut input = String::new();
+    fn sequence(line: &str) -> Sequence {
+        return parse(line).remove(0);
+    }
 
-    io::stdin()
-        .read_to_string(&mut input)
-        .expect("Failed to read input");
+    #[test]
+    fn test_polynomial() {
+        let sequences = parse(INPUT);
+        let triangular = &sequences[1];
+        let polynomial = triangular.polynomial().unwrap();
+        assert_eq!(polynomial.degree(), Some(2));
+        let half = BigRational::new(BigInt::from(1), BigInt::from(2));
+        assert_eq!(polynomial.coefficients(), vec![BigRational::one(), &half * BigInt::from(3), half]);
+
+        assert_eq!(polynomial.evaluate(0), BigInt::from(1));
+        assert_eq!(polynomial.evaluate(6), BigInt::from(28));
+        assert_eq!(polynomial.evaluate(-1), BigInt::from(0));
+        assert_eq!(polynomial.evaluate(-5), BigInt::from(6));
+        assert_eq!(triangular.extrapolate(3), Ok(BigInt::from(45)));
+        assert_eq!(triangular.extrapolate(-2), Ok(BigInt::from(0)));
+        assert_eq!(sequences[2].extrapolate(1), Ok(BigInt::from(68)));
+        assert_eq!(sequences[2].extrapolate(-1), Ok(BigInt::from(5)));
+        assert_eq!(sequences[2].polynomial().unwrap().degree(), Some(3));
+
+        let far: BigInt = "1000000000000000000000000000001".parse().unwrap();
+        assert_eq!(sequence("1 2 5 10").polynomial().unwrap().evaluate(1_000_000_000_000_000), far);
+        assert_eq!(sequence("7 7 7").polynomial().unwrap().degree(), Some(0));
+        assert_eq!(sequence("0 0").polynomial().unwrap().degree(), None);
+        assert_eq!(sequence("0 0").extrapolate(5), Ok(BigInt::from(0)));
+    }
 
+    #[test]
+    fn test_overflow() {
+        let big = sequence("9223372036854775807 -9223372036854775808 9223372036854775807 64563604257983430652");
+        assert_eq!(big.extrapolate(1), Ok("156797324626531188727".parse().unwrap()));
+        assert_eq!(sequence("-9223372036854775808 9223372036854775807 27670116110564327422").extrapolate(1),
+            Ok("46116860184273879037".parse().unwrap()));
+    }
 
-    for f in compute {
-        let result = f(parse(&input));
-        println!("{}", result);    
+    #[test]
+    fn test_not_polynomial() {
+        assert_eq!(sequence("1 2 4 8 16").polynomial().err(),
+            Some("differences of 1 2 4 8 16 never reach all zeroes".to_owned()));
+        assert_eq!(sequence("5").extrapolate(1), Err("differences of 5 never reach all zeroes".to_owned()));
+        assert_eq!(sequence("0").extrapolate(1), Ok(BigInt::from(0)));
+        assert_eq!(Sequence(vec![]).extrapolate(1), Err("empty sequence".to_owned()));
+        let input = parse(indoc! {"
+            0 3 6 9 12 15
+            1 1 2 3 5 8 13
+
+            1 2 3
+        "});
+        assert_eq!(compute_1(input), Err("sequence 2: differences of 1 1 2 3 5 8 13 never reach all zeroes".to_owned()));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,330 @@
+use std::fmt;
+
+// half-open interval [start, end)
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Interval {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Interval {
+    pub fn new(start: u64, end: u64) -> Interval {
+        assert!(start < end, "empty interval [{}, {})", start, end);
+        Interval { start, end }
+    }
+
+    pub fn with_length(start: u64, length: u64) -> Interval {
+        return Interval::new(start, start + length);
+    }
+
+    pub fn length(&self) -> u64 {
+        return self.end - self.start;
+    }
+
+    pub fn contains(&self, x: u64) -> bool {
+        return self.start <= x && x < self.end;
+    }
+
+    pub fn intersect(&self, other: Interval) -> Option<Interval> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start < end { Some(Interval::new(start, end)) } else { None }
+    }
+
+    // splits `to_split` in the parts before, inside and after `self`
+    pub fn split(&self, to_split: Interval) -> (Option<Interval>, Option<Interval>, Option<Interval>) {
+        let before = if to_split.start >= self.start {
+            None
+        } else {
+            Some(Interval::new(to_split.start, to_split.end.min(self.start)))
+        };
+
+        let intersect = self.intersect(to_split);
+
+        let after = if to_split.end <= self.end {
+            None
+        } else {
+            Some(Interval::new(to_split.start.max(self.end), to_split.end))
+        };
+
+        return (before, intersect, after);
+    }
+
+    pub fn shift(&self, offset: i64) -> Interval {
+        return Interval::new(shift(self.start, offset), shift(self.end - 1, offset) + 1);
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {})", self.start, self.end)
+    }
+}
+
+pub fn shift(x: u64, offset: i64) -> u64 {
+    return x.checked_add_signed(offset)
+        .unwrap_or_else(|| panic!("{} + {} does not fit in u64", x, offset));
+}
+
+// sorts and merges overlapping or adjacent intervals
+pub fn merge(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort();
+    let mut result: Vec<Interval> = Vec::new();
+    for i in intervals {
+        match result.last_mut() {
+            Some(last) if i.start <= last.end => last.end = last.end.max(i.end),
+            _ => result.push(i),
+        }
+    }
+    return result;
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Piece {
+    pub src: Interval,
+    pub offset: i64,
+}
+
+impl Piece {
+    pub fn dest(&self) -> Interval {
+        return self.src.shift(self.offset);
+    }
+}
+
+// piecewise-linear map on [0, u64::MAX): every x in a piece maps to x + offset,
+// everything outside the pieces maps to itself
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct IntervalMap {
+    // sorted, non-overlapping, without zero offsets, adjacent pieces have different offsets
+    pieces: Vec<Piece>,
+}
+
+impl IntervalMap {
+    pub fn identity() -> IntervalMap {
+        return IntervalMap { pieces: Vec::new() };
+    }
+
+    // overlapping pieces are resolved by first match
+    pub fn from_pieces(pieces: impl IntoIterator<Item = Piece>) -> IntervalMap {
+        let mut covered: Vec<Interval> = Vec::new();
+        let mut result = Vec::new();
+        for piece in pieces {
+            let mut uncovered = vec![piece.src];
+            for c in &covered {
+                uncovered = uncovered.into_iter()
+                    .flat_map(|u| {
+                        let (before, _, after) = c.split(u);
+                        [before, after]
+                    })
+                    .flatten()
+                    .collect();
+            }
+            result.extend(uncovered.into_iter().map(|src| Piece { src, offset: piece.offset }));
+            covered = merge([covered, vec![piece.src]].concat());
+        }
+        return IntervalMap::normalized(result);
+    }
+
+    fn normalized(mut pieces: Vec<Piece>) -> IntervalMap {
+        pieces.retain(|p| p.offset != 0);
+        pieces.sort_by_key(|p| p.src);
+        let mut result: Vec<Piece> = Vec::new();
+        for p in pieces {
+            match result.last_mut() {
+                Some(last) if last.src.end == p.src.start && last.offset == p.offset => last.src.end = p.src.end,
+                _ => {
+                    if let Some(last) = result.last() {
+                        assert!(last.src.end <= p.src.start, "overlapping pieces {:?} and {:?}", last, p);
+                    }
+                    result.push(p)
+                },
+            }
+        }
+        return IntervalMap { pieces: result };
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        return &self.pieces;
+    }
+
+    pub fn get(&self, x: u64) -> u64 {
+        let idx = self.pieces.partition_point(|p| p.src.end <= x);
+        match self.pieces.get(idx) {
+            Some(p) if p.src.contains(x) => shift(x, p.offset),
+            _ => x,
+        }
+    }
+
+    // partitions `within` into the parts covered by a piece and the identity parts in between
+    pub fn segments(&self, within: Interval) -> Vec<Piece> {
+        let mut result = Vec::new();
+        let mut pos = within.start;
+        let first = self.pieces.partition_point(|p| p.src.end <= within.start);
+        for p in self.pieces[first..].iter().take_while(|p| p.src.start < within.end) {
+            if pos < p.src.start {
+                result.push(Piece { src: Interval::new(pos, p.src.start), offset: 0 });
+            }
+            let src = p.src.intersect(within).unwrap();
+            result.push(Piece { src, offset: p.offset });
+            pos = src.end;
+        }
+        if pos < within.end {
+            result.push(Piece { src: Interval::new(pos, within.end), offset: 0 });
+        }
+        return result;
+    }
+
+    pub fn map_interval(&self, interval: Interval) -> Vec<Interval> {
+        return self.segments(interval).iter().map(|p| p.dest()).collect();
+    }
+
+    // the map x -> then(self(x))
+    pub fn compose(&self, then: &IntervalMap) -> IntervalMap {
+        let mut result = Vec::new();
+        for first in self.segments(Interval::new(0, u64::MAX)) {
+            for second in then.segments(first.dest()) {
+                result.push(Piece {
+                    src: second.src.shift(-first.offset),
+                    offset: first.offset + second.offset,
+                });
+            }
+        }
+        return IntervalMap::normalized(result);
+    }
+
+    // all x for which self(x) lies in `target`
+    pub fn preimage(&self, target: Interval) -> Vec<Interval> {
+        let mut result = Vec::new();
+        for segment in self.segments(Interval::new(0, u64::MAX)) {
+            if let Some(hit) = segment.dest().intersect(target) {
+                result.push(hit.shift(-segment.offset));
+            }
+        }
+        return merge(result);
+    }
+
+    // only bijective maps have an inverse
+    pub fn invert(&self) -> Option<IntervalMap> {
+        let sources: Vec<Interval> = self.pieces.iter().map(|p| p.src).collect();
+        let dests: Vec<Interval> = self.pieces.iter().map(|p| p.dest()).collect();
+        let dest_len: u64 = dests.iter().map(|d| d.length()).sum();
+        let merged_dests = merge(dests.clone());
+        if merged_dests.iter().map(|d| d.length()).sum::<u64>() != dest_len || merged_dests != merge(sources) {
+            return None;
+        }
+        let inverted = self.pieces.iter().map(|p| Piece { src: p.dest(), offset: -p.offset }).collect();
+        return Some(IntervalMap::normalized(inverted));
+    }
+}
+
+impl fmt::Display for IntervalMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for p in &self.pieces {
+            writeln!(f, "{} -> {} ({:+})", p.src, p.dest(), p.offset)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iv(start: u64, end: u64) -> Interval {
+        Interval::new(start, end)
+    }
+
+    fn piece(start: u64, end: u64, offset: i64) -> Piece {
+        Piece { src: iv(start, end), offset }
+    }
+
+    #[test]
+    fn test_split() {
+        let r1 = iv(1, 11);
+        let r2 = iv(2, 4);
+        let r5 = iv(37, 44);
+        assert_eq!(r1.split(r2), (None, Some(r2), None));
+        assert_eq!(r2.split(r1), (Some(iv(1, 2)), Some(r2), Some(iv(4, 11))));
+        assert_eq!(r5.split(r1), (Some(r1), None, None));
+        assert_eq!(r1.split(r5), (None, None, Some(r5)));
+        assert_eq!(r1.split(r1), (None, Some(r1), None));
+    }
+
+    #[test]
+    fn test_merge() {
+        assert_eq!(merge(vec![iv(5, 7), iv(1, 3), iv(3, 4), iv(6, 9)]), vec![iv(1, 4), iv(5, 9)]);
+        assert_eq!(merge(vec![]), vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shift_overflow() {
+        iv(u64::MAX - 10, u64::MAX).shift(20);
+    }
+
+    #[test]
+    fn test_get() {
+        let map = IntervalMap::from_pieces([piece(98, 100, -48), piece(50, 98, 2)]);
+        assert_eq!(map.get(0), 0);
+        assert_eq!(map.get(49), 49);
+        assert_eq!(map.get(50), 52);
+        assert_eq!(map.get(79), 81);
+        assert_eq!(map.get(97), 99);
+        assert_eq!(map.get(98), 50);
+        assert_eq!(map.get(99), 51);
+        assert_eq!(map.get(100), 100);
+    }
+
+    #[test]
+    fn test_normalization() {
+        let map = IntervalMap::from_pieces([
+            piece(0, 10, 5),
+            piece(5, 20, 7),
+            piece(20, 30, 7),
+            piece(30, 40, 0),
+        ]);
+        assert_eq!(map.pieces(), &[piece(0, 10, 5), piece(10, 30, 7)]);
+        assert_eq!(IntervalMap::from_pieces([piece(1, 2, 0)]), IntervalMap::identity());
+    }
+
+    #[test]
+    fn test_map_interval() {
+        let map = IntervalMap::from_pieces([piece(10, 20, 100)]);
+        assert_eq!(map.map_interval(iv(5, 25)), vec![iv(5, 10), iv(110, 120), iv(20, 25)]);
+        assert_eq!(map.map_interval(iv(12, 13)), vec![iv(112, 113)]);
+        assert_eq!(map.map_interval(iv(0, 3)), vec![iv(0, 3)]);
+    }
+
+    #[test]
+    fn test_compose() {
+        let a = IntervalMap::from_pieces([piece(0, 10, 10), piece(10, 20, -10)]);
+        let b = IntervalMap::from_pieces([piece(5, 15, 100)]);
+        let ab = a.compose(&b);
+        for x in 0..30 {
+            assert_eq!(ab.get(x), b.get(a.get(x)), "at {}", x);
+        }
+        assert_eq!(a.compose(&a), IntervalMap::identity());
+        assert_eq!(a.compose(&IntervalMap::identity()), a);
+        assert_eq!(IntervalMap::identity().compose(&b), b);
+    }
+
+    #[test]
+    fn test_preimage() {
+        let map = IntervalMap::from_pieces([piece(0, 10, 50)]);
+        // 50..60 is reached both from 0..10 and from itself
+        assert_eq!(map.preimage(iv(55, 65)), vec![iv(5, 10), iv(55, 65)]);
+        assert_eq!(map.preimage(iv(0, 5)), vec![]);
+    }
+
+    #[test]
+    fn test_invert() {
+        let swap = IntervalMap::from_pieces([piece(0, 10, 20), piece(10, 30, -10)]);
+        let inverse = swap.invert().unwrap();
+        for x in 0..40 {
+            assert_eq!(inverse.get(swap.get(x)), x);
+        }
+        assert_eq!(IntervalMap::from_pieces([piece(0, 10, 50)]).invert(), None);
+        assert_eq!(IntervalMap::from_pieces([piece(0, 10, 5), piece(10, 15, -5)]).invert(), None);
+        assert_eq!(IntervalMap::identity().invert(), Some(IntervalMap::identity()));
+    }
+}